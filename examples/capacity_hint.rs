@@ -0,0 +1,83 @@
+// Compares allocations for a cloning search before and after boards kept
+// room for their pieces. Before, each step cloned the board, which only
+// keeps room for the pieces already placed, and then added a piece, which
+// had to grow the pieces. After, the search starts from
+// `Board::with_capacity_hint` and `add_clone` keeps the room.
+//
+// Usage: cargo run --release --example capacity_hint [WIDTH HEIGHT]
+
+use polyomino_generator::board::Board;
+use polyomino_generator::polyominos::{Polyomino, ALL_POLYOMINOS};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// The plain depth-first search, cloning the board for every piece that fits.
+fn count_full<F>(board: &Board, pieces: &[&'static Polyomino], add_clone: &F) -> usize
+where F: Fn(&Board, &'static Polyomino) -> Option<Board> {
+    if board.is_full() {
+        return 1;
+    }
+    pieces.iter()
+        .filter_map(|&poly| add_clone(board, poly))
+        .map(|next| count_full(&next, pieces, add_clone))
+        .sum()
+}
+
+fn run<F>(name: &str, start: Board, pieces: &[&'static Polyomino], add_clone: F)
+where F: Fn(&Board, &'static Polyomino) -> Option<Board> {
+    let time = Instant::now();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let count = count_full(&start, pieces, &add_clone);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{}: {} boards, {} allocations, {:?}", name, count, allocations, time.elapsed());
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let (width, height) = if args.len() == 3 {
+        (args[1].parse().unwrap(), args[2].parse().unwrap())
+    } else {
+        (5, 5)
+    };
+
+    // Trominoes and tetrominoes keep a 5x5 run short.
+    let pieces: Vec<_> = ALL_POLYOMINOS.iter().filter(|p| p.size() >= 3).collect();
+    let most_pieces = width * height / 3;
+
+    run("before", Board::new(width, height), &pieces, |board, poly| {
+        // Only clone when the piece fits, as add_clone always has.
+        let first_open = board.empty_cells().next()?;
+        if !board.fits_at(poly, first_open) {
+            return None;
+        }
+        let mut next = board.clone();
+        next.add(poly);
+        Some(next)
+    });
+    run("after ", Board::with_capacity_hint(width, height, most_pieces), &pieces, Board::add_clone);
+}
@@ -0,0 +1,22 @@
+// Times generating every polyomino up to a size, symmetry tables included.
+//
+// Usage: cargo run --release --example generation_time [MAX_SIZE [RUNS]]
+
+use polyomino_generator::polyominos::catalog;
+use std::env;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let max_size = args.get(1).map_or(6, |arg| arg.parse().unwrap());
+    let runs = args.get(2).map_or(20, |arg| arg.parse().unwrap());
+
+    let mut best = Duration::MAX;
+    let mut free = 0;
+    for _ in 0..runs {
+        let start = Instant::now();
+        free = catalog(max_size).len();
+        best = best.min(start.elapsed());
+    }
+    println!("size {}: {} free polyominos, best of {}: {:?}", max_size, free, runs, best);
+}
@@ -0,0 +1,55 @@
+// Compares allocations between the cloning search and the in-place search.
+//
+// Usage: cargo run --release --example search_allocations [WIDTH HEIGHT]
+
+use polyomino_generator::polyominos::ALL_POLYOMINOS;
+use polyomino_generator::solver::Solver;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let (width, height) = if args.len() == 3 {
+        (args[1].parse().unwrap(), args[2].parse().unwrap())
+    } else {
+        (5, 5)
+    };
+
+    // Trominoes and tetrominoes keep a 5x5 run short.
+    let pieces = ALL_POLYOMINOS.iter().filter(|p| p.size() >= 3).collect();
+    let solver = Solver::new(width, height).with_pieces(pieces);
+
+    let start = Instant::now();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut count = 0;
+    solver.for_each_full_board(|_| count += 1);
+    let cloned = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("cloning:  {} boards, {} allocations, {:?}", count, cloned, start.elapsed());
+
+    let start = Instant::now();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut count = 0;
+    solver.for_each_full_board_in_place(|_| count += 1);
+    let in_place = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("in place: {} boards, {} allocations, {:?}", count, in_place, start.elapsed());
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use crate::board::Board;
+use crate::polyominos::Polyomino;
+use crate::solver::Constraint;
+use crate::symmetry::Symmetry;
+
+/// Gives every piece a cost and keeps the total cost of a board under a
+/// limit. Branches are pruned as soon as they go over, or once the total
+/// overflows a u32. Constraints can't keep state between placements, so
+/// each check adds up the cost of every piece already on the board.
+pub struct Budget<F> {
+    cost: F,
+    limit: u32
+}
+
+impl<F> Budget<F>
+where F: Fn(&Polyomino) -> u32 {
+    pub fn new(cost: F, limit: u32) -> Budget<F> {
+        Budget { cost, limit }
+    }
+
+    /// The total cost of the pieces on `board`, or None if it overflows.
+    pub fn spent(&self, board: &Board) -> Option<u32> {
+        board.pieces().iter().try_fold(0u32, |total, poly| total.checked_add((self.cost)(poly)))
+    }
+}
+
+impl<F> Constraint for Budget<F>
+where F: Fn(&Polyomino) -> u32 {
+    fn allows_placement(&self, board: &Board, poly: &'static Polyomino, _base: (i8, i8)) -> bool {
+        self.spent(board)
+            .and_then(|spent| spent.checked_add((self.cost)(poly)))
+            .is_some_and(|total| total <= self.limit)
+    }
+}
+
+/// Only accepts boards that use each chiral piece exactly as often as its
+/// mirror image, counting every rotation of a piece as the same piece. So a
+/// board with two L tetrominos needs two J tetrominos.
+pub struct ChiralBalance;
+
+impl Constraint for ChiralBalance {
+    fn accepts_solution(&self, board: &Board) -> bool {
+        let mut counts = BTreeMap::new();
+        for poly in board.pieces() {
+            *counts.entry(poly.one_sided_form()).or_insert(0) += 1;
+        }
+        counts.iter().all(|(poly, count)| {
+            !poly.is_chiral() || counts.get(poly.mirror_form()) == Some(count)
+        })
+    }
+}
+
+/// Allows at most `max` monominos as fillers, for boards whose area the
+/// other pieces can't cover exactly. The monomino still has to be in the
+/// solver's pieces; this only limits how often it's used.
+pub struct Fillers {
+    max: usize
+}
+
+impl Fillers {
+    pub fn new(max: usize) -> Fillers {
+        Fillers { max }
+    }
+
+    /// Where the fillers landed on `board`, in reading order.
+    pub fn positions(board: &Board) -> Vec<(i8, i8)> {
+        let mut ret = Vec::new();
+        for y in 0..board.height() as i8 {
+            for x in 0..board.width() as i8 {
+                if let Some(Some(index)) = board.get(x, y) {
+                    if board.pieces()[index].size() == 1 {
+                        ret.push((x, y));
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl Constraint for Fillers {
+    fn allows_placement(&self, board: &Board, poly: &'static Polyomino, _base: (i8, i8)) -> bool {
+        poly.size() != 1 || board.pieces().iter().filter(|p| p.size() == 1).count() < self.max
+    }
+}
+
+/// Only accepts boards where no four pieces meet at a single point, so every
+/// inner grid vertex touches at most three pieces. Only full boards are
+/// checked, nothing is pruned early.
+pub struct NoFourPieceCorners;
+
+impl NoFourPieceCorners {
+    /// The inner grid vertices of `board` where four different pieces meet,
+    /// in reading order. (x, y) is the top left corner of cell (x, y).
+    pub fn corners(board: &Board) -> Vec<(i8, i8)> {
+        let mut ret = Vec::new();
+        for y in 1..board.height() as i8 {
+            for x in 1..board.width() as i8 {
+                let around = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)];
+                let pieces: Vec<_> = around.iter().map(|&(x, y)| board.get(x, y).flatten()).collect();
+                let distinct = pieces.iter().enumerate()
+                    .all(|(i, piece)| piece.is_some() && !pieces[..i].contains(piece));
+                if distinct {
+                    ret.push((x, y));
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl Constraint for NoFourPieceCorners {
+    fn accepts_solution(&self, board: &Board) -> bool {
+        NoFourPieceCorners::corners(board).is_empty()
+    }
+}
+
+/// Only accepts boards whose tiling maps onto itself under a symmetry, like
+/// `Symmetry::default().rotate(2)` for tilings that look the same upside
+/// down. Only full boards are checked, nothing is pruned early.
+pub struct RequireSymmetry(pub Symmetry);
+
+impl Constraint for RequireSymmetry {
+    fn accepts_solution(&self, board: &Board) -> bool {
+        board.board_symmetries().contains(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+    use crate::polyominos::ALL_POLYOMINOS;
+    use crate::board::Solution;
+    use crate::solver::Solver;
+
+    // Small pieces are expensive.
+    fn cost(poly: &Polyomino) -> u32 {
+        5 - poly.size() as u32
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget::new(cost, 3);
+        let limited = Solver::new(4, 2).with_constraint(Budget::new(cost, 3)).solve().solutions;
+        let unlimited = Solver::new(4, 2).solve().solutions;
+        assert!(!limited.is_empty());
+
+        let spent = |solution| budget.spent(&Board::from_solution(4, 2, solution).unwrap()).unwrap();
+        for solution in &limited {
+            assert!(spent(solution) <= 3);
+            assert!(unlimited.contains(solution));
+        }
+        // Every solution that fits the budget is still found.
+        let affordable = unlimited.iter().filter(|s| spent(s) <= 3).count();
+        assert_eq!(limited.len(), affordable);
+        assert!(affordable < unlimited.len());
+
+        // A single monomino costs more than the whole budget.
+        assert!(limited.iter().all(|s| s.iter().all(|p| p.size() > 1)));
+
+        // Costs that add up past u32::MAX are over any budget. Every 4x2
+        // tiling has at least two pieces.
+        let half = |_: &Polyomino| u32::MAX / 2;
+        let two_pieces = |s: &&Solution| s.len() == 2;
+        let pairs = Solver::new(4, 2).with_constraint(Budget::new(half, u32::MAX)).solve().solutions;
+        assert_eq!(pairs, unlimited.iter().filter(two_pieces).cloned().collect::<Vec<_>>());
+        let huge = |_: &Polyomino| u32::MAX / 2 + 1;
+        assert!(Solver::new(4, 2).with_constraint(Budget::new(huge, u32::MAX)).solve().solutions.is_empty());
+        let board = Board::from_solution(4, 2, &pairs[0]).unwrap();
+        assert_eq!(Budget::new(huge, u32::MAX).spent(&board), None);
+    }
+
+    #[test]
+    fn test_chiral_balance() {
+        // X
+        // X
+        // XX
+        let l = ALL_POLYOMINOS.iter().find(|p| p.cells() == [(0, 0), (0, 1), (0, 2), (1, 2)]).unwrap();
+        let solver = || Solver::new(4, 4).with_free_pieces(&[l]);
+
+        let balanced = solver().with_constraint(ChiralBalance).solve().solutions;
+        let all = solver().solve().solutions;
+        assert!(!balanced.is_empty());
+        assert!(balanced.len() < all.len());
+
+        for solution in &balanced {
+            let ls = solution.iter().filter(|p| p.one_sided_form() == l.one_sided_form()).count();
+            let js = solution.iter().filter(|p| p.one_sided_form() == l.mirror_form()).count();
+            assert_eq!(ls, js, "{:?}", solution);
+        }
+    }
+
+    #[test]
+    fn test_require_symmetry() {
+        let half_turn = Symmetry::default().rotate(2);
+        let symmetric = Solver::new(4, 3).with_constraint(RequireSymmetry(half_turn)).solve().solutions;
+        let all = Solver::new(4, 3).solve().solutions;
+        assert!(!symmetric.is_empty());
+        assert!(symmetric.len() < all.len());
+
+        let board = |solution| Board::from_solution(4, 3, solution).unwrap();
+        for solution in &symmetric {
+            assert!(board(solution).board_symmetries().contains(&half_turn));
+        }
+        let expected = all.iter().filter(|s| board(s).board_symmetries().contains(&half_turn)).count();
+        assert_eq!(symmetric.len(), expected);
+    }
+
+    #[test]
+    fn test_no_four_piece_corners() {
+        let mono = ALL_POLYOMINOS.iter().find(|p| p.size() == 1).unwrap();
+        let square = ALL_POLYOMINOS.iter().find(|p| p.cells() == [(0, 0), (0, 1), (1, 0), (1, 1)]).unwrap();
+
+        // Four squares all meet in the middle.
+        let squares = Board::from_solution(4, 4, &Solver::new(4, 4).with_pieces(vec![square]).solve().solutions[0]).unwrap();
+        assert_eq!(NoFourPieceCorners::corners(&squares), vec![(2, 2)]);
+        assert!(!NoFourPieceCorners.accepts_solution(&squares));
+
+        let mut framed = Board::new(4, 2);
+        assert!(framed.place_at(square, (1, 0)));
+        for &base in &[(0, 0), (0, 1), (3, 0), (3, 1)] {
+            assert!(framed.place_at(mono, base));
+        }
+        assert_eq!(NoFourPieceCorners::corners(&framed), vec![]);
+        assert!(NoFourPieceCorners.accepts_solution(&framed));
+
+        let clean = Solver::new(4, 3).with_constraint(NoFourPieceCorners).solve().solutions;
+        let all = Solver::new(4, 3).solve().solutions;
+        assert!(!clean.is_empty());
+        assert!(clean.len() < all.len());
+        let board = |solution| Board::from_solution(4, 3, solution).unwrap();
+        let expected = all.iter().filter(|s| NoFourPieceCorners::corners(&board(s)).is_empty()).count();
+        assert_eq!(clean.len(), expected);
+    }
+
+    #[test]
+    fn test_fillers() {
+        let mut pieces: Vec<_> = ALL_POLYOMINOS.iter().filter(|p| p.size() == 4).collect();
+        pieces.push(ALL_POLYOMINOS.iter().find(|p| p.size() == 1).unwrap());
+        let solutions = Solver::new(3, 3)
+            .with_pieces(pieces.clone())
+            .with_constraint(Fillers::new(1))
+            .solve().solutions;
+        assert!(!solutions.is_empty());
+
+        let mut filler_cells = BTreeSet::new();
+        for solution in &solutions {
+            let board = Board::from_solution(3, 3, solution).unwrap();
+            let positions = Fillers::positions(&board);
+            assert_eq!(positions.len(), 1);
+            assert_eq!(board.get(positions[0].0, positions[0].1).flatten().map(|i| board.pieces()[i].size()), Some(1));
+            filler_cells.insert(positions[0]);
+        }
+        // Canonical forms put the filler in a corner, on an edge, or in the middle.
+        assert!(filler_cells.len() > 1);
+
+        // Without a filler a 3x3 can't be covered by tetrominos.
+        let none = Solver::new(3, 3).with_pieces(pieces).with_constraint(Fillers::new(0)).solve();
+        assert!(none.solutions.is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A polyomino was given more cells than a `Polyomino` can hold.
+    TooManyCells(usize),
+    /// A polyomino's coords didn't include (0, 0).
+    MissingOrigin,
+    /// A polyomino's cells weren't all joined together edge to edge.
+    Disconnected,
+    /// A cell was filled when it needed to be empty.
+    CellOccupied { x: i8, y: i8 },
+    /// A piece covered a cell that doesn't allow pieces of its size.
+    SizeNotAllowed { x: i8, y: i8 },
+    /// A coordinate was off the edge of the board.
+    OutOfBounds { x: i8, y: i8 },
+    /// Pieces were left over after the board was already full.
+    BoardFull,
+    /// A piece index was too big to store in a board cell.
+    PieceIndexTooLarge(usize),
+    /// A polyomino wasn't in the table it was looked up in.
+    PieceNotFound(Vec<(i8, i8)>),
+    /// Text couldn't be read as a board or polyomino.
+    ParseError(String)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::TooManyCells(count) => write!(f, "polyomino has too many cells ({})", count),
+            Error::MissingOrigin => write!(f, "polyomino doesn't contain (0, 0)"),
+            Error::Disconnected => write!(f, "polyomino isn't connected"),
+            Error::CellOccupied { x, y } => write!(f, "cell ({}, {}) is already occupied", x, y),
+            Error::SizeNotAllowed { x, y } => write!(f, "cell ({}, {}) doesn't allow a piece of that size", x, y),
+            Error::OutOfBounds { x, y } => write!(f, "cell ({}, {}) is out of bounds", x, y),
+            Error::BoardFull => write!(f, "board is already full"),
+            Error::PieceIndexTooLarge(index) => write!(f, "piece index {} is too large", index),
+            Error::PieceNotFound(coords) => write!(f, "no polyomino with coords {:?}", coords),
+            Error::ParseError(message) => write!(f, "parse error: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,8 @@
+pub mod polyominos;
+pub mod board;
+pub mod symmetry;
+pub mod solver;
+pub mod constraints;
+mod error;
+
+pub use error::Error;
@@ -1,21 +1,33 @@
 
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 use std::hash::{Hash, Hasher};
 use tinyvec::ArrayVec;
 use crate::symmetry::Symmetry;
+use crate::Error;
 
 use lazy_static::lazy_static;
 
+/// The most cells a `Polyomino` can have. Only pieces up to size 4 are in
+/// `ALL_POLYOMINOS`; bigger ones are only generated for the `catalog`.
+/// Every piece stores this many coords inline: going from 6 to 8 took a
+/// `Polyomino` from 96 to 104 bytes, and `ALL_POLYOMINOS` from 2688 to 2912.
+pub const MAX_CELLS: usize = 8;
+
 lazy_static! {
     pub static ref ALL_POLYOMINOS: Vec<Polyomino> = generate_all_polyominos(4);
 }
 
-#[derive(Debug, Clone, Eq, Ord)]
+#[derive(Debug, Clone, Eq)]
 pub struct Polyomino {
-    coords: ArrayVec<[(i8, i8); 4]>,
-    symmetries: Option<[usize; 8]>
+    coords: ArrayVec<[(i8, i8); MAX_CELLS]>,
+    symmetries: Option<[usize; 8]>,
+    // A hash of the sorted coords. Kept in sync with them so lookups can
+    // skip most unequal pieces without comparing every coord.
+    fingerprint: u32,
+    // min_x, max_x, min_y, max_y of the coords, also kept in sync.
+    bounds: (i8, i8, i8, i8)
 }
 
 impl Polyomino {
@@ -30,20 +42,53 @@ impl Polyomino {
         }
     }
 
-    fn new(coords: &[(i8, i8)]) -> Polyomino {
-        if coords.len() > 4 {
-            panic!();
+    fn new(coords: &[(i8, i8)]) -> Result<Polyomino, Error> {
+        if coords.len() > MAX_CELLS {
+            return Err(Error::TooManyCells(coords.len()));
         }
         if !coords.contains(&(0, 0)) {
-            panic!();
+            return Err(Error::MissingOrigin);
+        }
+        if !is_connected(coords) {
+            return Err(Error::Disconnected);
         }
-        let mut actual_coords = ArrayVec::from_iter(coords.iter().map(|coord| *coord));
+        let mut actual_coords = ArrayVec::from_iter(coords.iter().copied());
         actual_coords.sort_by(Self::coord_sort);
 
-        Polyomino {
+        Ok(Polyomino {
+            fingerprint: Self::compute_fingerprint(&actual_coords),
+            bounds: Self::compute_bounds(&actual_coords),
             coords: actual_coords,
             symmetries: None
+        })
+    }
+
+    fn compute_bounds(coords: &[(i8, i8)]) -> (i8, i8, i8, i8) {
+        coords.iter().fold(
+            (i8::MAX, i8::MIN, i8::MAX, i8::MIN),
+            |(min_x, max_x, min_y, max_y), &(x, y)| {
+                (i8::min(min_x, x), i8::max(max_x, x), i8::min(min_y, y), i8::max(max_y, y))
+            }
+        )
+    }
+
+    // 32 bit FNV-1a over the coords, which must already be sorted.
+    fn compute_fingerprint(coords: &[(i8, i8)]) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        for &(x, y) in coords {
+            for byte in [x as u8, y as u8] {
+                hash ^= byte as u32;
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
         }
+        hash
+    }
+
+    /// A cheap hash of this polyomino's shape. Equal polyominos always have
+    /// the same fingerprint, so different fingerprints mean different shapes.
+    #[inline]
+    pub fn fingerprint(&self) -> u32 {
+        self.fingerprint
     }
 
     #[inline]
@@ -56,6 +101,40 @@ impl Polyomino {
         self.coords.iter()
     }
 
+    /// The cells relative to the (0, 0) anchor, followed by their min_x,
+    /// max_x, min_y and max_y. min_y is always 0, and min_x is negative when
+    /// the piece reaches left of its anchor.
+    #[inline]
+    pub fn offsets(&self) -> (&[(i8, i8)], i8, i8, i8, i8) {
+        let (min_x, max_x, min_y, max_y) = self.bounds;
+        (&self.coords, min_x, max_x, min_y, max_y)
+    }
+
+    /// Whether `a`, with its (0, 0) coord at `a_base`, shares any cell with
+    /// `b` at `b_base`.
+    pub fn overlaps(a: &Polyomino, a_base: (i8, i8), b: &Polyomino, b_base: (i8, i8)) -> bool {
+        // Work relative to a, and skip the cells if the bounds don't meet.
+        let (dx, dy) = (b_base.0 - a_base.0, b_base.1 - a_base.1);
+        let (a_min_x, a_max_x, a_min_y, a_max_y) = a.bounds;
+        let (b_min_x, b_max_x, b_min_y, b_max_y) = b.bounds;
+        if b_min_x + dx > a_max_x || b_max_x + dx < a_min_x || b_min_y + dy > a_max_y || b_max_y + dy < a_min_y {
+            return false;
+        }
+        b.coords.iter().any(|&(x, y)| a.coords.contains(&(x + dx, y + dy)))
+    }
+
+    /// The width and height of the smallest rectangle the polyomino fits in.
+    pub fn bounding_box(&self) -> (u8, u8) {
+        let (min_x, max_x, min_y, max_y) = self.bounds;
+        ((max_x - min_x + 1) as u8, (max_y - min_y + 1) as u8)
+    }
+
+    /// The same coords as `coords`, sorted, as a slice.
+    #[inline]
+    pub fn cells(&self) -> &[(i8, i8)] {
+        &self.coords
+    }
+
     fn apply_flips(&mut self, transform: Symmetry) {
         // First apply the transformations.
         for (x, y) in &mut self.coords {
@@ -75,9 +154,7 @@ impl Polyomino {
         // y is more important).
         let mut top_left_most_coord = (i8::MAX, i8::MAX);
         for &(x, y) in &self.coords {
-            if y < top_left_most_coord.1 {
-                top_left_most_coord = (x, y);
-            } else if y == top_left_most_coord.1 && x < top_left_most_coord.0 {
+            if y < top_left_most_coord.1 || (y == top_left_most_coord.1 && x < top_left_most_coord.0) {
                 top_left_most_coord = (x, y);
             }
         }
@@ -89,8 +166,11 @@ impl Polyomino {
 
         // Finally sort the coords
         self.coords.sort_by(Self::coord_sort);
+        self.fingerprint = Self::compute_fingerprint(&self.coords);
+        self.bounds = Self::compute_bounds(&self.coords);
     }
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         let mut max_x = i8::MIN;
         let mut max_y = i8::MIN;
@@ -121,7 +201,10 @@ impl Polyomino {
     }
 
     // Can't be mutable because it needs to access the array that contains itself.
-    fn compute_transforms(&self, all_polyominos: &[Polyomino]) -> [usize; 8] {
+    // `assign_symmetries` does the same thing a class at a time; this is the
+    // straightforward version it's checked against.
+    #[cfg(test)]
+    fn compute_transforms(&self, all_polyominos: &[Polyomino]) -> Result<[usize; 8], Error> {
         let mut matching_polyominos = Vec::with_capacity(8);
         matching_polyominos.resize(8, self.clone());
 
@@ -131,66 +214,144 @@ impl Polyomino {
 
         let mut indices: [usize; 8] = Default::default();
         for (i, poly) in matching_polyominos.into_iter().enumerate() {
-            let index = match all_polyominos.iter().position(|e| e == &poly) {
-                Some(p) => p,
-                None => {
-                    panic!("Could not find poly: \n{}({:?})\n\nSimilar polyominos:\n{:?}", poly.to_string(), poly.coords,
-                        all_polyominos.iter().filter(|p| p.size() == poly.size()).map(|p| p.to_string()).collect::<Vec<_>>())
-                }
-            };
-            indices[i] = index;
+            indices[i] = all_polyominos.iter()
+                .position(|e| e.fingerprint == poly.fingerprint && e == &poly)
+                .ok_or_else(|| Error::PieceNotFound(poly.coords.to_vec()))?;
         }
 
-        indices
+        Ok(indices)
+    }
+
+    /// Whether copies of this polyomino can tile the infinite plane. Every
+    /// polyomino up to size 6 can. Bigger ones are only reported as tiling if
+    /// they tile by translation alone, so this is conservative for pieces that
+    /// need to be rotated or flipped to tile.
+    pub fn tiles_plane(&self) -> bool {
+        self.size() <= 6 || tiles_by_translation(&self.coords)
     }
 
     pub fn transform(&self, symmetry: Symmetry) -> &'static Polyomino {
         &ALL_POLYOMINOS[self.symmetries.unwrap()[symmetry.into_index()]]
     }
+
+    // Like `transform`, but also works for polyominos that aren't in
+    // `ALL_POLYOMINOS`, by flipping the coords directly.
+    fn transformed(&self, symmetry: Symmetry) -> Polyomino {
+        if self.symmetries.is_some() {
+            return self.transform(symmetry).clone();
+        }
+        let mut ret = self.clone();
+        ret.apply_flips(symmetry);
+        ret
+    }
+
+    /// This polyomino mirrored left-to-right.
+    pub fn reflect_horizontal(&self) -> Polyomino {
+        self.transformed(Symmetry::from_flips(true, false, false))
+    }
+
+    /// This polyomino mirrored top-to-bottom.
+    pub fn reflect_vertical(&self) -> Polyomino {
+        self.transformed(Symmetry::from_flips(false, true, false))
+    }
+
+    /// This polyomino turned 90 degrees clockwise.
+    pub fn rotate_cw(&self) -> Polyomino {
+        self.transformed(Symmetry::default().rotate(1))
+    }
+
+    /// This polyomino turned 90 degrees counterclockwise.
+    pub fn rotate_ccw(&self) -> Polyomino {
+        self.transformed(Symmetry::default().rotate(-1))
+    }
+
+    /// The rotation of this polyomino that sorts first. Two polyominos have
+    /// the same one-sided form exactly when one is a rotation of the other.
+    pub fn one_sided_form(&self) -> &'static Polyomino {
+        (0..4)
+            .map(|turns| self.transform(Symmetry::default().rotate(turns)))
+            .min()
+            .unwrap()
+    }
+
+    /// Whether this polyomino's mirror image can't be reached by rotating it,
+    /// like the L and J tetrominos.
+    pub fn is_chiral(&self) -> bool {
+        self.mirror_form() != self.one_sided_form()
+    }
+
+    /// The one-sided form of this polyomino's mirror image.
+    pub fn mirror_form(&self) -> &'static Polyomino {
+        self.transform(Symmetry::from_flips(true, false, false)).one_sided_form()
+    }
+
+    /// Every distinct rotation and reflection of this polyomino.
+    pub fn orientations(&self) -> Vec<&'static Polyomino> {
+        let mut ret: Vec<&'static Polyomino> = Vec::with_capacity(8);
+        for symmetry in Symmetry::ALL_SYMMETRIES {
+            let poly = self.transform(symmetry);
+            if !ret.contains(&poly) {
+                ret.push(poly);
+            }
+        }
+        ret
+    }
 }
 
 impl PartialEq for Polyomino {
     fn eq(&self, other: &Polyomino) -> bool {
         // Ignore the symmetry field, which is more like a cache than a part of the poly.
-        self.coords == other.coords
+        // Unequal fingerprints settle most comparisons without the coords.
+        self.fingerprint == other.fingerprint && self.coords == other.coords
     }
 }
 
-// Matching hash function for custom PartialEq implementation
+// Matching hash function for custom PartialEq implementation. The fingerprint
+// already hashes the coords, so hash lookups (as in `assign_symmetries`) only
+// feed the hasher four bytes and then compare fingerprints first.
 impl Hash for Polyomino {
     fn hash<H>(&self, state: &mut H)
     where H: Hasher {
-        self.coords.hash(state);
+        self.fingerprint.hash(state);
+    }
+}
+
+/// Smaller polyominos sort first. Polyominos of the same size are compared
+/// coord by coord. Like `PartialEq`, this ignores the symmetry cache.
+impl Ord for Polyomino {
+    fn cmp(&self, other: &Polyomino) -> Ordering {
+        self.coords.len().cmp(&other.coords.len())
+            .then_with(|| {
+                self.coords.iter()
+                    .zip(other.coords.iter())
+                    .map(|(a, b)| Polyomino::coord_sort(a, b))
+                    .find(|&ordering| ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
     }
 }
 
 impl PartialOrd for Polyomino {
     fn partial_cmp(&self, other: &Polyomino) -> Option<Ordering> {
-        if self.coords.len() > other.coords.len() {
-            return Some(Ordering::Greater);
-        } else if self.coords.len() < other.coords.len() {
-            return Some(Ordering::Less);
-        }
-
-        for i in 0..self.coords.len() {
-            match Polyomino::coord_sort(&self.coords[i], &other.coords[i]) {
-                Ordering::Greater => return Some(Ordering::Greater),
-                Ordering::Less => return Some(Ordering::Less),
-                Ordering::Equal => ()
-            }
-        }
-
-        Some(Ordering::Equal)
+        Some(self.cmp(other))
     }
 }
 
 // Sorted smallest-first
 fn generate_all_polyominos(max_size: usize) -> Vec<Polyomino> {
+    generate_polyominos(max_size, 0, |_| ())
+}
+
+// Calls `on_progress` with the number of distinct polyominos found so far
+// every time another `every` of them are found. Never calls it if `every`
+// is 0.
+fn generate_polyominos<F>(max_size: usize, every: usize, mut on_progress: F) -> Vec<Polyomino>
+where F: FnMut(usize) {
     let mut stack = Vec::new();
     let mut polyominos = HashSet::new();
 
     let base = vec![(0, 0)];
-    polyominos.insert(Polyomino::new(&base));
+    polyominos.insert(Polyomino::new(&base).expect("a single cell is a polyomino"));
     if max_size > 1 {
         stack.push(base);
     }
@@ -199,7 +360,10 @@ fn generate_all_polyominos(max_size: usize) -> Vec<Polyomino> {
         for coord in adjacent_coords(&polyomino) {
             let mut new_poly = polyomino.clone();
             new_poly.push(coord);
-            polyominos.insert(Polyomino::new(&new_poly));
+            let poly = Polyomino::new(&new_poly).expect("growing a polyomino keeps it connected");
+            if polyominos.insert(poly) && every != 0 && polyominos.len().is_multiple_of(every) {
+                on_progress(polyominos.len());
+            }
             if new_poly.len() < max_size {
                 stack.push(new_poly);
             }
@@ -209,13 +373,278 @@ fn generate_all_polyominos(max_size: usize) -> Vec<Polyomino> {
     let mut ret: Vec<_> = polyominos.into_iter().collect();
     ret.sort();
 
-    for i in 0..ret.len() {
-        let symmetries = ret[i].compute_transforms(&ret);
-        assert_eq!(symmetries[0], i);
-        ret[i].symmetries = Some(symmetries);
+    assign_symmetries(&mut ret);
+    ret
+}
+
+// Fills in the symmetry table of every polyomino in `polyominos`, which must
+// contain every flip of each of them, exactly once.
+//
+// The flips of a polyomino are only worked out for the first member of each
+// free polyomino's class. Every other member is one of those flips, and its
+// own flips are just the class's flips composed with the one that reached it.
+fn assign_symmetries(polyominos: &mut [Polyomino]) {
+    let mut compose = [[0; 8]; 8];
+    for (first, row) in compose.iter_mut().enumerate() {
+        for (second, composed) in row.iter_mut().enumerate() {
+            let symmetry = Symmetry::ALL_SYMMETRIES[first].then(Symmetry::ALL_SYMMETRIES[second]);
+            *composed = symmetry.into_index();
+        }
     }
 
-    ret
+    let mut indices = HashMap::with_capacity(polyominos.len());
+    for (i, poly) in polyominos.iter().enumerate() {
+        indices.entry(poly).or_insert(i);
+    }
+
+    let mut tables: Vec<Option<[usize; 8]>> = vec![None; polyominos.len()];
+    for i in 0..polyominos.len() {
+        if tables[i].is_some() {
+            continue;
+        }
+        let mut flips = [0; 8];
+        for (symmetry, flip) in Symmetry::ALL_SYMMETRIES.iter().zip(&mut flips) {
+            let mut poly = polyominos[i].clone();
+            poly.apply_flips(*symmetry);
+            *flip = *indices.get(&poly).expect("every flip of a generated polyomino is also generated");
+        }
+        // The identity has to find the polyomino itself. If it doesn't, there
+        // are duplicates, or the sort and the equality check disagree.
+        if flips[0] != i {
+            panic!(
+                "polyomino {:?} at index {} maps to index {} under the identity",
+                polyominos[i].coords.as_slice(), i, flips[0]
+            );
+        }
+        for (first, &member) in flips.iter().enumerate() {
+            let table = tables[member].get_or_insert([0; 8]);
+            for (second, flip) in table.iter_mut().enumerate() {
+                *flip = flips[compose[first][second]];
+            }
+        }
+    }
+
+    for (poly, table) in polyominos.iter_mut().zip(tables) {
+        poly.symmetries = table;
+    }
+}
+
+/// Every free polyomino with up to `max_size` cells (at most `MAX_CELLS`),
+/// as its size and its drawing, smallest first. Each one is drawn in
+/// whichever orientation sorts first.
+pub fn catalog(max_size: usize) -> Vec<(u8, String)> {
+    catalog_with_progress(max_size, 0, |_| ())
+}
+
+/// Same as `catalog`, but calls `on_progress` with the number of fixed
+/// polyominos (counting each rotation and reflection separately) found so
+/// far, every time another `every` of them are found.
+pub fn catalog_with_progress<F>(max_size: usize, every: usize, on_progress: F) -> Vec<(u8, String)>
+where F: FnMut(usize) {
+    generate_polyominos(max_size, every, on_progress).iter()
+        .enumerate()
+        .filter(|(i, poly)| poly.symmetries.unwrap().iter().min() == Some(i))
+        .map(|(_, poly)| (poly.size(), poly.to_string()))
+        .collect()
+}
+
+/// The pieces in `ALL_POLYOMINOS` whose bounding box is at most
+/// `max_width` wide and `max_height` tall, in the orientation they're placed
+/// in. So a height of 1 only leaves the horizontal straight pieces.
+pub fn polyominos_within(max_width: u8, max_height: u8) -> Vec<&'static Polyomino> {
+    polyominos_where(|poly| {
+        let (width, height) = poly.bounding_box();
+        width <= max_width && height <= max_height
+    })
+}
+
+/// Yields every free polyomino with up to `max_size` cells (at most
+/// `MAX_CELLS`), each in the orientation that sorts first, without keeping
+/// the ones already found. They come out in no particular order. Their
+/// symmetries aren't looked up, so `transform` doesn't work on them; the
+/// named transforms like `rotate_cw` do.
+pub fn free_polyominos(max_size: usize) -> FreePolyominos {
+    assert!(max_size <= MAX_CELLS, "polyominos can have at most {} cells", MAX_CELLS);
+    FreePolyominos {
+        max_size,
+        cells: Vec::with_capacity(max_size),
+        untried: if max_size == 0 { Vec::new() } else { vec![vec![(0, 0)]] }
+    }
+}
+
+/// The iterator returned by `free_polyominos`.
+///
+/// Uses Redelmeier's method, which grows every fixed polyomino exactly once
+/// from (0, 0), so nothing has to be remembered to skip repeats. Cells only
+/// grow down, or right along the top row, so (0, 0) stays the top-left cell.
+pub struct FreePolyominos {
+    max_size: usize,
+    // The cells of the polyomino being grown.
+    cells: Vec<(i8, i8)>,
+    // For each cell being chosen, the cells it could still be.
+    untried: Vec<Vec<(i8, i8)>>
+}
+
+impl Iterator for FreePolyominos {
+    type Item = Polyomino;
+
+    fn next(&mut self) -> Option<Polyomino> {
+        loop {
+            let depth = self.untried.len();
+            // Take back the cell chosen last time at this depth.
+            if self.cells.len() == depth {
+                self.cells.pop();
+            }
+            let cell = match self.untried.last_mut()?.pop() {
+                Some(cell) => cell,
+                None => {
+                    self.untried.pop();
+                    continue;
+                }
+            };
+
+            if self.cells.len() + 1 < self.max_size {
+                // Only neighbors that no earlier cell could have grown into
+                // are new. The rest were already tried, or still will be.
+                let mut next = self.untried.last().unwrap().clone();
+                let (x, y) = cell;
+                for neighbor in [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)] {
+                    let (nx, ny) = neighbor;
+                    let in_half_plane = ny > 0 || (ny == 0 && nx >= 0);
+                    let touches_earlier = self.cells.iter()
+                        .any(|&(cx, cy)| (cx - nx).abs() + (cy - ny).abs() <= 1);
+                    if in_half_plane && !touches_earlier {
+                        next.push(neighbor);
+                    }
+                }
+                self.untried.push(next);
+            }
+            self.cells.push(cell);
+
+            let poly = Polyomino::new(&self.cells).expect("grown cells are a polyomino");
+            let is_first = Symmetry::ALL_SYMMETRIES.iter()
+                .all(|&symmetry| poly <= poly.transformed(symmetry));
+            if is_first {
+                return Some(poly);
+            }
+        }
+    }
+}
+
+/// The pieces in `ALL_POLYOMINOS` that pass `filter`, for handing to the
+/// solver.
+pub fn polyominos_where<F>(filter: F) -> Vec<&'static Polyomino>
+where F: Fn(&Polyomino) -> bool {
+    ALL_POLYOMINOS.iter().filter(|p| filter(p)).collect()
+}
+
+// Beauquier-Nivat: a polyomino without holes tiles the plane by translation
+// iff its boundary word can be written as A B C Â B̂ Ĉ, where X̂ is X walked
+// backwards. C may be empty.
+fn tiles_by_translation(coords: &[(i8, i8)]) -> bool {
+    let word = match boundary_word(coords) {
+        Some(word) => word,
+        None => return false
+    };
+    let n = word.len();
+    let half = n / 2;
+    let backwards = |(dx, dy): (i8, i8)| (-dx, -dy);
+    // Does word[start..start + len] walked backwards match word[other..other + len]?
+    let matches = |start: usize, other: usize, len: usize| {
+        (0..len).all(|i| backwards(word[(start + i) % n]) == word[(other + len - 1 - i) % n])
+    };
+
+    for start in 0..n {
+        for a in 1..half {
+            for b in 1..=(half - a) {
+                let c = half - a - b;
+                if matches(start, start + half, a)
+                    && matches(start + a, start + half + a, b)
+                    && matches(start + a + b, start + half + a + b, c) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+// The unit steps taken walking clockwise around the outside of the cells, or
+// None if the cells enclose a hole or only touch at a corner somewhere on the
+// boundary.
+fn boundary_word(coords: &[(i8, i8)]) -> Option<Vec<(i8, i8)>> {
+    if has_hole(coords) {
+        return None;
+    }
+
+    let mut edges = HashMap::new();
+    for &(x, y) in coords {
+        let sides = [
+            ((x, y - 1), (x, y), (1, 0)),
+            ((x + 1, y), (x + 1, y), (0, 1)),
+            ((x, y + 1), (x + 1, y + 1), (-1, 0)),
+            ((x - 1, y), (x, y + 1), (0, -1))
+        ];
+        for &(neighbor, start, step) in &sides {
+            if !coords.contains(&neighbor) && edges.insert(start, step).is_some() {
+                return None;
+            }
+        }
+    }
+
+    let start = *edges.keys().min()?;
+    let mut word = Vec::with_capacity(edges.len());
+    let mut current = start;
+    loop {
+        let step = edges[&current];
+        word.push(step);
+        current = (current.0 + step.0, current.1 + step.1);
+        if current == start {
+            break;
+        }
+    }
+    if word.len() == edges.len() {
+        Some(word)
+    } else {
+        None
+    }
+}
+
+// Whether some empty cell inside the bounding box can't reach the outside.
+fn has_hole(coords: &[(i8, i8)]) -> bool {
+    let min_x = coords.iter().map(|c| c.0).min().unwrap_or(0) - 1;
+    let max_x = coords.iter().map(|c| c.0).max().unwrap_or(0) + 1;
+    let min_y = coords.iter().map(|c| c.1).min().unwrap_or(0) - 1;
+    let max_y = coords.iter().map(|c| c.1).max().unwrap_or(0) + 1;
+
+    let mut outside = HashSet::new();
+    let mut stack = vec![(min_x, min_y)];
+    while let Some((x, y)) = stack.pop() {
+        if x < min_x || x > max_x || y < min_y || y > max_y || coords.contains(&(x, y)) {
+            continue;
+        }
+        if outside.insert((x, y)) {
+            stack.extend_from_slice(&[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+        }
+    }
+
+    let area = (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize;
+    outside.len() + coords.len() < area
+}
+
+/// Whether every coord can reach every other one through coords that share
+/// an edge. No coords at all count as connected, and repeated coords don't.
+pub fn is_connected(coords: &[(i8, i8)]) -> bool {
+    let mut reached = Vec::with_capacity(coords.len());
+    let mut stack: Vec<_> = coords.first().copied().into_iter().collect();
+    while let Some((x, y)) = stack.pop() {
+        if reached.contains(&(x, y)) || !coords.contains(&(x, y)) {
+            continue;
+        }
+        reached.push((x, y));
+        stack.extend_from_slice(&[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+    }
+    reached.len() == coords.len()
 }
 
 fn adjacent_coords(polyomino: &[(i8, i8)]) -> Vec<(i8, i8)> {
@@ -233,14 +662,15 @@ fn adjacent_coords(polyomino: &[(i8, i8)]) -> Vec<(i8, i8)> {
         .collect()
 }
 
-#[allow(unused_imports)]
+#[allow(unused_imports, clippy::bool_assert_comparison, clippy::useless_vec)]
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::BTreeSet;
 
     fn find_global_poly(coords: Vec<(i8, i8)>) -> &'static Polyomino {
         for poly in ALL_POLYOMINOS.iter() {
-            if poly.coords().map(|&c| c).collect::<Vec<_>>() == coords {
+            if poly.cells() == coords.as_slice() {
                 return poly;
             }
         }
@@ -252,19 +682,19 @@ mod test {
         let test = vec![(0, 1), (1, 0), (0, 0)];
         let sorted = vec![(0, 0), (0, 1), (1, 0)];
         
-        let poly = Polyomino::new(&test);
+        let poly = Polyomino::new(&test).unwrap();
         assert_eq!(poly.coords.to_vec(), sorted);
 
         let test = vec![(1, 0), (0, 0), (0, 2), (0, 1)];
         let sorted = vec![(0, 0), (0, 1), (0, 2), (1, 0)];
 
-        let poly = Polyomino::new(&test);
+        let poly = Polyomino::new(&test).unwrap();
         assert_eq!(poly.coords.to_vec(), sorted);
 
         let test = vec![(0, 0)];
         let sorted = vec![(0, 0)];
 
-        let poly = Polyomino::new(&test);
+        let poly = Polyomino::new(&test).unwrap();
         assert_eq!(poly.coords.to_vec(), sorted);
     }
 
@@ -306,7 +736,7 @@ mod test {
         // XX
         let hvd_leg = vec![(0, 0), (0, 1), (0, 2), (1, 2)];
 
-        let poly = Polyomino::new(&leg);
+        let poly = Polyomino::new(&leg).unwrap();
         let mut h_poly = poly.clone();
         let mut v_poly = poly.clone();
         let mut hv_poly = poly.clone();
@@ -335,7 +765,7 @@ mod test {
             poly, h_poly, v_poly, hv_poly,
             d_poly, hd_poly, vd_poly, hvd_poly
         ];
-        let transforms = all_poly[0].compute_transforms(&all_poly);
+        let transforms = all_poly[0].compute_transforms(&all_poly).unwrap();
         let index = Symmetry::from_flips(false, false, false).into_index();
         let h_index = Symmetry::from_flips(true, false, false).into_index();
         let v_index = Symmetry::from_flips(false, true, false).into_index();
@@ -354,6 +784,54 @@ mod test {
         assert_eq!(transforms[hvd_index], 7);
     }
 
+    #[test]
+    fn test_is_connected() {
+        let plus = [(0, 0), (-1, 1), (0, 1), (1, 1), (0, 2)];
+        assert!(is_connected(&plus));
+        let islands = [(0, 0), (1, 0), (3, 0), (3, 1)];
+        assert!(!is_connected(&islands));
+        // Touching corners isn't enough.
+        assert!(!is_connected(&[(0, 0), (1, 1)]));
+
+        assert!(is_connected(&[]));
+        assert!(is_connected(&[(5, -3)]));
+        assert!(!is_connected(&[(0, 0), (0, 0)]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let too_many: Vec<_> = (0..9).map(|x| (x, 0)).collect();
+        assert_eq!(Polyomino::new(&too_many), Err(Error::TooManyCells(9)));
+
+        let no_origin = [(1, 0), (2, 0)];
+        assert_eq!(Polyomino::new(&no_origin), Err(Error::MissingOrigin));
+
+        let islands = [(0, 0), (2, 0)];
+        assert_eq!(Polyomino::new(&islands), Err(Error::Disconnected));
+
+        let domino = Polyomino::new(&[(0, 0), (1, 0)]).unwrap();
+        let unit = Polyomino::new(&[(0, 0)]).unwrap();
+        match domino.compute_transforms(&[unit]) {
+            Err(Error::PieceNotFound(coords)) => assert_eq!(coords, vec![(0, 0), (1, 0)]),
+            other => panic!("{:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_cells() {
+        for poly in ALL_POLYOMINOS.iter() {
+            assert_eq!(poly.cells().len(), poly.size() as usize);
+            assert_eq!(poly.cells(), poly.coords().copied().collect::<Vec<_>>().as_slice());
+
+            let mut sorted = poly.cells().to_vec();
+            sorted.sort_by(Polyomino::coord_sort);
+            assert_eq!(poly.cells(), sorted.as_slice());
+        }
+
+        let leg = Polyomino::new(&[(1, 0), (0, 0), (0, 2), (0, 1)]).unwrap();
+        assert_eq!(leg.cells(), &[(0, 0), (0, 1), (0, 2), (1, 0)]);
+    }
+
     #[test]
     fn test_to_string() {
         // X
@@ -362,7 +840,7 @@ mod test {
         let test = vec![(0, 0), (0, 1), (1, 1), (1, 2)];
         let output = "@ \n##\n #\n";
 
-        let poly = Polyomino::new(&test);
+        let poly = Polyomino::new(&test).unwrap();
         assert_eq!(poly.to_string(), output);
     }
 
@@ -397,13 +875,243 @@ mod test {
         assert_eq!(d_flip.transform(Symmetry::from_flips(false, false, true)), original);
     }
 
+    #[test]
+    fn test_tiles_plane() {
+        for poly in ALL_POLYOMINOS.iter().filter(|p| p.size() == 4) {
+            assert!(poly.tiles_plane(), "{}", poly.to_string());
+            assert!(tiles_by_translation(poly.cells()), "{}", poly.to_string());
+        }
+        assert_eq!(polyominos_where(Polyomino::tiles_plane).len(), ALL_POLYOMINOS.len());
+
+        // XXX
+        // X X
+        // XX
+        let holed_heptomino = Polyomino::new(&[(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2)]).unwrap();
+        assert!(!holed_heptomino.tiles_plane());
+
+        // The same piece with the hole filled in tiles fine.
+        let filled = Polyomino::new(&[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)]).unwrap();
+        assert!(filled.tiles_plane());
+    }
+
+    #[test]
+    fn test_catalog() {
+        let small = catalog(3);
+        let sizes: Vec<_> = small.iter().map(|&(size, _)| size).collect();
+        assert_eq!(sizes, vec![1, 2, 3, 3]);
+        assert_eq!(small[0].1, "@\n");
+        assert_eq!(small[1].1, "@\n#\n");
+
+        assert_eq!(catalog(4).len(), 1 + 1 + 2 + 5);
+        assert_eq!(catalog(6).len(), 1 + 1 + 2 + 5 + 12 + 35);
+    }
+
+    #[test]
+    fn test_polyominos_within() {
+        let flat = polyominos_within(4, 1);
+        assert_eq!(flat.len(), 4);
+        for (size, poly) in (1..=4).zip(&flat) {
+            assert_eq!(poly.size(), size);
+            assert!(poly.cells().iter().all(|&(_, y)| y == 0));
+        }
+
+        let band = polyominos_within(4, 2);
+        assert!(band.iter().all(|poly| poly.bounding_box().1 <= 2));
+        assert!(band.len() > flat.len() && band.len() < ALL_POLYOMINOS.len());
+
+        // The result can go straight to the solver. A 4x1 strip splits into
+        // 8 runs of lengths, 6 of them different up to reversal.
+        let solutions = crate::solver::Solver::new(4, 1).with_pieces(flat).solve().solutions;
+        assert_eq!(solutions.len(), 6);
+    }
+
+    #[test]
+    fn test_free_polyominos() {
+        let expected: BTreeSet<_> = ALL_POLYOMINOS.iter()
+            .enumerate()
+            .filter(|(i, poly)| poly.symmetries.unwrap().iter().min() == Some(i))
+            .map(|(_, poly)| poly.clone())
+            .collect();
+        let streamed: Vec<_> = free_polyominos(4).collect();
+        assert_eq!(streamed.len(), expected.len());
+        assert_eq!(streamed.into_iter().collect::<BTreeSet<_>>(), expected);
+
+        // 1, 1, 2, 5, 12, 35, 108 and 369 free polyominos of each size.
+        assert_eq!(free_polyominos(8).count(), 533);
+        assert_eq!(free_polyominos(8).filter(|poly| poly.size() == 7).count(), 108);
+        assert_eq!(free_polyominos(0).count(), 0);
+        assert_eq!(free_polyominos(1).count(), 1);
+    }
+
+    #[test]
+    fn test_catalog_progress() {
+        // There are 1 + 2 + 6 + 19 + 63 fixed polyominos up to size 5.
+        let mut calls = Vec::new();
+        let pentominos = catalog_with_progress(5, 10, |found| calls.push(found));
+        assert_eq!(calls, vec![10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        assert_eq!(pentominos, catalog(5));
+        assert_eq!(pentominos.iter().filter(|&&(size, _)| size == 5).count(), 12);
+
+        let mut called = false;
+        catalog_with_progress(5, 0, |_| called = true);
+        assert!(!called);
+    }
+
+    #[test]
+    #[should_panic(expected = "polyomino [(0, 0)] at index 1 maps to index 0 under the identity")]
+    fn test_duplicate_symmetries() {
+        let mono = Polyomino::new(&[(0, 0)]).unwrap();
+        assign_symmetries(&mut [mono.clone(), mono]);
+    }
+
+    #[test]
+    fn test_symmetry_tables() {
+        // Flip every polyomino and look the flips up the slow way.
+        let polyominos = generate_all_polyominos(7);
+        for poly in &polyominos {
+            assert_eq!(poly.symmetries, Some(poly.compute_transforms(&polyominos).unwrap()), "{:?}", poly);
+        }
+
+        // Monomino, horizontal domino, vertical domino, ...
+        let tables: Vec<_> = ALL_POLYOMINOS.iter().take(3).map(|poly| poly.symmetries.unwrap()).collect();
+        assert_eq!(tables, vec![
+            [0; 8],
+            [1, 1, 1, 1, 2, 2, 2, 2],
+            [2, 2, 2, 2, 1, 1, 1, 1]
+        ]);
+    }
+
+    #[test]
+    fn test_transform_round_trip() {
+        // Flips happen before the diagonal swap, so undoing a symmetry with
+        // a diagonal swaps which way it flips.
+        let inverse = |s: Symmetry| if s.diagonal {
+            Symmetry::from_flips(s.vertical, s.horizontal, true)
+        } else {
+            s
+        };
+
+        for poly in ALL_POLYOMINOS.iter() {
+            for symmetry in Symmetry::ALL_SYMMETRIES {
+                let transformed = poly.transform(symmetry);
+                assert_eq!(transformed.transform(inverse(symmetry)), poly);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offsets() {
+        // An S tetromino reaches one cell left of its anchor.
+        let s_piece = Polyomino::new(&[(0, 0), (1, 0), (-1, 1), (0, 1)]).unwrap();
+        let (cells, min_x, max_x, min_y, max_y) = s_piece.offsets();
+        assert_eq!(cells, s_piece.cells());
+        assert_eq!((min_x, max_x, min_y, max_y), (-1, 1, 0, 1));
+        assert_eq!(s_piece.bounding_box(), (3, 2));
+
+        for poly in ALL_POLYOMINOS.iter() {
+            let (cells, min_x, max_x, min_y, max_y) = poly.offsets();
+            assert_eq!(min_y, 0);
+            assert!(cells.contains(&(0, 0)));
+            assert!(cells.iter().all(|&(x, y)| min_x <= x && x <= max_x && min_y <= y && y <= max_y));
+            assert!(cells.iter().any(|&(x, _)| x == min_x) && cells.iter().any(|&(x, _)| x == max_x));
+            assert!(cells.iter().any(|&(_, y)| y == max_y));
+            assert_eq!(poly.bounding_box(), ((max_x - min_x + 1) as u8, (max_y + 1) as u8));
+
+            // Turning a piece swaps its width and height.
+            let (width, height) = poly.bounding_box();
+            assert_eq!(poly.rotate_cw().bounding_box(), (height, width));
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let l = Polyomino::new(&[(0, 0), (0, 1), (0, 2), (1, 2)]).unwrap();
+        let shuffled = Polyomino::new(&[(1, 2), (0, 1), (0, 0), (0, 2)]).unwrap();
+        assert_eq!(l.fingerprint(), shuffled.fingerprint());
+        assert_eq!(l.fingerprint(), l.clone().fingerprint());
+
+        // Flipping back and forth ends up with the same fingerprint too.
+        let registered = ALL_POLYOMINOS.iter().find(|&p| *p == l).unwrap();
+        assert_eq!(l.fingerprint(), registered.fingerprint());
+        assert_eq!(l.fingerprint(), l.rotate_cw().rotate_ccw().fingerprint());
+
+        // No two of the registered polyominos collide.
+        let fingerprints: HashSet<_> = ALL_POLYOMINOS.iter().map(|p| p.fingerprint()).collect();
+        assert_eq!(fingerprints.len(), ALL_POLYOMINOS.len());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let square = Polyomino::new(&[(0, 0), (1, 0), (0, 1), (1, 1)]).unwrap();
+        let s_piece = Polyomino::new(&[(0, 0), (1, 0), (-1, 1), (0, 1)]).unwrap();
+
+        // Side by side, and one above the other.
+        assert!(!Polyomino::overlaps(&square, (0, 0), &square, (2, 0)));
+        assert!(!Polyomino::overlaps(&square, (0, 0), &square, (0, 2)));
+        assert!(Polyomino::overlaps(&square, (0, 0), &square, (1, 1)));
+        assert!(Polyomino::overlaps(&square, (3, 3), &square, (3, 3)));
+
+        // The S reaches left of its anchor, into the square's column.
+        assert!(Polyomino::overlaps(&square, (0, 0), &s_piece, (2, 0)));
+        assert!(!Polyomino::overlaps(&square, (0, 0), &s_piece, (3, 0)));
+        // Their bounding boxes overlap, but their cells don't.
+        assert!(!Polyomino::overlaps(&s_piece, (1, 0), &square, (-1, -1)));
+        assert!(Polyomino::overlaps(&s_piece, (2, 0), &square, (0, 0)));
+    }
+
+    #[test]
+    fn test_named_transforms() {
+        let l = Polyomino::new(&[(0, 0), (0, 1), (0, 2), (1, 2)]).unwrap();
+        let registered = ALL_POLYOMINOS.iter().find(|&p| *p == l).unwrap();
+
+        for p in [&l, registered] {
+            assert_eq!(p.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), *p);
+            assert_eq!(p.rotate_cw().rotate_ccw(), *p);
+            assert_eq!(p.reflect_horizontal().reflect_horizontal(), *p);
+            assert_eq!(p.reflect_vertical().reflect_vertical(), *p);
+            assert_ne!(p.rotate_cw(), *p);
+            assert_ne!(p.reflect_horizontal(), *p);
+        }
+
+        // The registered path looks the answer up, the other flips coords.
+        assert_eq!(l.rotate_cw(), registered.rotate_cw());
+        assert_eq!(l.reflect_vertical(), registered.reflect_vertical());
+        // Turning twice is the same as flipping both ways.
+        assert_eq!(l.rotate_cw().rotate_cw(), l.reflect_horizontal().reflect_vertical());
+    }
+
+    #[test]
+    fn test_one_sided_form() {
+        // X
+        // X
+        // XX
+        let l = find_global_poly(vec![(0, 0), (0, 1), (0, 2), (1, 2)]);
+        //  X
+        //  X
+        // XX
+        let j = find_global_poly(vec![(-1, 2), (0, 0), (0, 1), (0, 2)]);
+        // XXX
+        //  X
+        let t = find_global_poly(vec![(0, 0), (1, 0), (1, 1), (2, 0)]);
+
+        for turns in 0..4 {
+            let rotated = l.transform(Symmetry::default().rotate(turns));
+            assert_eq!(rotated.one_sided_form(), l.one_sided_form());
+        }
+        assert_ne!(l.one_sided_form(), j.one_sided_form());
+        assert_eq!(l.mirror_form(), j.one_sided_form());
+        assert!(l.is_chiral());
+        assert!(!t.is_chiral());
+        assert_eq!(t.mirror_form(), t.one_sided_form());
+    }
+
     #[test]
     fn test_comparison() {
-        let small_tall = Polyomino::new(&vec![(0, 0), (0, 1)]);
-        let big_square = Polyomino::new(&vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        let small_tall = Polyomino::new(&vec![(0, 0), (0, 1)]).unwrap();
+        let big_square = Polyomino::new(&vec![(0, 0), (0, 1), (1, 0), (1, 1)]).unwrap();
         assert_eq!(small_tall < big_square, true, "{:?} < {:?}", small_tall, big_square);
 
-        let small_flat = Polyomino::new(&vec![(0, 0), (1, 0)]);
+        let small_flat = Polyomino::new(&vec![(0, 0), (1, 0)]).unwrap();
         assert_eq!(small_tall < small_flat, true, "{:?} < {:?}", small_tall, small_flat);
 
         let mut all = vec![small_flat.clone(), big_square.clone(), small_tall.clone()];
@@ -412,12 +1120,18 @@ mod test {
 
         //  XX
         // XX
-        let zig_wide = Polyomino::new(&vec![(-1, 1), (0, 0), (0, 1), (1, 0)]);
+        let zig_wide = Polyomino::new(&vec![(-1, 1), (0, 0), (0, 1), (1, 0)]).unwrap();
 
         //  X
         // XX
         // X
-        let zig_tall = Polyomino::new(&vec![(-1, 1), (-1, 2), (0, 0), (0, 1)]);
+        let zig_tall = Polyomino::new(&vec![(-1, 1), (-1, 2), (0, 0), (0, 1)]).unwrap();
         assert_eq!(zig_tall < zig_wide, true, "{:?} < {:?}", zig_tall, zig_wide);
+
+        for a in ALL_POLYOMINOS.iter() {
+            for b in ALL_POLYOMINOS.iter() {
+                assert_eq!(a.cmp(b), a.partial_cmp(b).unwrap(), "{:?} vs {:?}", a, b);
+            }
+        }
     }
 }
\ No newline at end of file
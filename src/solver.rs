@@ -0,0 +1,1491 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Solution};
+use crate::polyominos::{Polyomino, ALL_POLYOMINOS};
+use crate::symmetry::Symmetry;
+use crate::Error;
+
+/// Picks the empty cell the search should cover next, or None if the board
+/// is full.
+pub type CellOrder = Box<dyn Fn(&Board) -> Option<(i8, i8)>>;
+
+/// Fills the board top-to-bottom, left-to-right.
+pub fn first_open_cell() -> CellOrder {
+    Box::new(|board| board.find_first_open_cell())
+}
+
+/// Fills the board row by row like `first_open_cell`, but alternates
+/// direction, running right on even rows and left on odd rows, so each row
+/// starts next to where the last one ended.
+pub fn snake_order() -> CellOrder {
+    Box::new(|board| {
+        for y in 0..board.height() as i8 {
+            let width = board.width() as i8;
+            for i in 0..width {
+                let x = if y % 2 == 0 { i } else { width - 1 - i };
+                if board.get(x, y) == Some(None) {
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Fills the empty cell with the fewest placements that could cover it.
+/// Cells that nothing can cover are picked immediately, so dead ends are
+/// abandoned as early as possible.
+pub fn min_remaining_placements(pieces: Vec<&'static Polyomino>) -> CellOrder {
+    Box::new(move |board| {
+        let mut best: Option<((i8, i8), usize)> = None;
+        for y in 0..board.height() as i8 {
+            for x in 0..board.width() as i8 {
+                if board.get(x, y) != Some(None) {
+                    continue;
+                }
+                let count: usize = pieces.iter()
+                    .map(|&poly| board.placements_covering(poly, (x, y)).count())
+                    .sum();
+                if count == 0 {
+                    return Some((x, y));
+                }
+                if best.is_none_or(|(_, best_count)| count < best_count) {
+                    best = Some(((x, y), count));
+                }
+            }
+        }
+        best.map(|(cell, _)| cell)
+    })
+}
+
+/// The tiling problem as an exact cover matrix, for handing off to other
+/// solvers. Each row is one placement that fits on `board`, listed as the
+/// columns it covers: one column per empty cell (in reading order), followed
+/// by one column per entry in `pieces` so that each piece is used exactly
+/// once. Solvers that allow repeated pieces can treat the piece columns as
+/// optional. Also returns the total number of columns.
+pub fn to_exact_cover(board: &Board, pieces: &[&Polyomino]) -> (Vec<Vec<usize>>, usize) {
+    let mut cell_columns = vec![None; board.width() * board.height()];
+    let mut empty_cells = Vec::new();
+    for y in 0..board.height() as i8 {
+        for x in 0..board.width() as i8 {
+            if board.get(x, y) == Some(None) {
+                cell_columns[x as usize + y as usize * board.width()] = Some(empty_cells.len());
+                empty_cells.push((x, y));
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (piece_index, poly) in pieces.iter().enumerate() {
+        // Every piece contains (0, 0), so every placement has its base on an empty cell.
+        for &(base_x, base_y) in &empty_cells {
+            if !board.fits_at(poly, (base_x, base_y)) {
+                continue;
+            }
+            let mut row: Vec<_> = poly.coords()
+                .map(|(x, y)| {
+                    let index = (base_x + x) as usize + (base_y + y) as usize * board.width();
+                    cell_columns[index].unwrap()
+                })
+                .collect();
+            row.sort_unstable();
+            row.push(empty_cells.len() + piece_index);
+            rows.push(row);
+        }
+    }
+
+    (rows, empty_cells.len() + pieces.len())
+}
+
+/// The fewest and most pieces any way of filling the empty cells of `board`
+/// could use, going only by the sizes of `pieces`. There may be no filling
+/// that uses exactly the fewest or the most, or any filling at all. Panics if
+/// `pieces` is empty.
+pub fn piece_count_bounds(board: &Board, pieces: &[&Polyomino]) -> (usize, usize) {
+    let cells = board.empty_cells().count();
+    let smallest = pieces.iter().map(|poly| poly.size() as usize).min().expect("no pieces");
+    let largest = pieces.iter().map(|poly| poly.size() as usize).max().expect("no pieces");
+    (cells.div_ceil(largest), cells / smallest)
+}
+
+/// Every tiling of a `width` by `height` rectangle using copies of `poly` in
+/// any orientation. Tilings that are symmetric copies of each other are all
+/// included.
+pub fn tile_with_single(width: usize, height: usize, poly: &'static Polyomino) -> Vec<Solution> {
+    if !(width * height).is_multiple_of(poly.size() as usize) {
+        return Vec::new();
+    }
+
+    let mut solutions = BTreeSet::new();
+    Solver::new(width, height)
+        .with_free_pieces(&[poly])
+        .for_each_full_board(|board| {
+            solutions.insert(board.solution());
+        });
+    solutions.into_iter().collect()
+}
+
+pub trait Constraint {
+    /// Checked before `poly` is placed at `base` on `board`. Returning false
+    /// prunes that branch of the search.
+    fn allows_placement(&self, _board: &Board, _poly: &'static Polyomino, _base: (i8, i8)) -> bool {
+        true
+    }
+
+    /// Checked once the board is full, or for `solve_uncovered`, once no more
+    /// pieces fit.
+    fn accepts_solution(&self, _board: &Board) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    MaxSolutions,
+    Timeout
+}
+
+/// How `write_solutions` writes each solution. Both write one line per
+/// solution, giving the board's rows in the run-length encoding of
+/// `Board::to_rle`, which `Board::from_rle` reads back however many pieces
+/// there are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolutionFormat {
+    /// The rows separated by slashes, like `2A1B/1A1C1B`.
+    Text,
+    /// A JSON object per line, like `{"id":"...","rows":["2A1B","1A1C1B"]}`,
+    /// where the id is the solution's canonical id in hex.
+    JsonLines
+}
+
+/// The result of `Solver::approximate_count`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApproximateCount {
+    /// How many distinct solutions the filter saw. Never more than the real
+    /// number, since the only mistake it makes is taking a new solution for
+    /// one it's already seen.
+    pub count: usize,
+    /// The chance, once the search finished, that a new solution would have
+    /// been mistaken for a seen one. Earlier solutions had less chance, so
+    /// `count` is short by less than about `count * false_positive_rate`.
+    pub false_positive_rate: f64,
+    /// Why the search stopped early, if it did. Then `count` only covers
+    /// the solutions found before it stopped.
+    pub reason: Option<StopReason>
+}
+
+// A bloom filter over canonical ids, using double hashing to get the bit
+// positions from one id.
+struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32
+}
+
+impl BloomFilter {
+    fn new(bits: usize, hashes: u32) -> BloomFilter {
+        BloomFilter { bits: vec![0; bits.div_ceil(64).max(1)], hashes }
+    }
+
+    fn len(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    // Sets the id's bits, and returns whether any of them weren't set yet.
+    fn insert(&mut self, id: u64) -> bool {
+        let step = Rng(id).next() | 1;
+        let mut added = false;
+        for i in 0..self.hashes as u64 {
+            let bit = id.wrapping_add(i.wrapping_mul(step)) % self.len();
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            added |= self.bits[word] & mask == 0;
+            self.bits[word] |= mask;
+        }
+        added
+    }
+
+    fn false_positive_rate(&self, inserted: usize) -> f64 {
+        let k = self.hashes as f64;
+        (1.0 - (-k * inserted as f64 / self.len() as f64).exp()).powf(k)
+    }
+}
+
+/// What a search found, and whether it looked everywhere. An incomplete
+/// search with no solutions doesn't mean the board can't be tiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOutcome {
+    pub solutions: Vec<Solution>,
+    pub completed: bool,
+    pub reason: Option<StopReason>
+}
+
+/// What `Solver::solve_uncovered` found. Like `SearchOutcome`, but with the
+/// boards themselves, since they can have empty cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncoveredOutcome {
+    pub boards: Vec<Board>,
+    pub completed: bool,
+    pub reason: Option<StopReason>
+}
+
+// One level of the in-place search: the cell being covered, the order the
+// pieces are tried in, and the next placement to try there.
+struct Move {
+    cell: (i8, i8),
+    order: PieceOrder,
+    piece: usize,
+    offset: usize,
+    placed: bool
+}
+
+// SplitMix64, which is plenty for shuffling and needs no dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+// The order one node tries the pieces in: the k-th piece tried is
+// `(start + k * step) % count`. `step` shares no factors with the piece count,
+// so every piece comes up once. This way the order doesn't need a list.
+#[derive(Clone, Copy)]
+struct PieceOrder {
+    start: usize,
+    step: usize
+}
+
+impl PieceOrder {
+    const IN_ORDER: PieceOrder = PieceOrder { start: 0, step: 1 };
+
+    fn new(rng: &mut Option<Rng>, count: usize) -> PieceOrder {
+        fn gcd(a: usize, b: usize) -> usize {
+            if b == 0 { a } else { gcd(b, a % b) }
+        }
+        match rng {
+            Some(rng) if count > 1 => {
+                let start = (rng.next() % count as u64) as usize;
+                let mut step = (rng.next() % count as u64) as usize;
+                while gcd(step, count) != 1 {
+                    step = (step + 1) % count;
+                }
+                PieceOrder { start, step }
+            },
+            _ => PieceOrder::IN_ORDER
+        }
+    }
+
+    fn index(&self, k: usize, count: usize) -> usize {
+        (self.start + k * self.step) % count
+    }
+}
+
+pub struct Solver {
+    width: usize,
+    height: usize,
+    pieces: Vec<&'static Polyomino>,
+    cell_order: CellOrder,
+    constraints: Vec<Box<dyn Constraint>>,
+    pins: Vec<(&'static Polyomino, (i8, i8))>,
+    allowed_sizes: Vec<((i8, i8), Vec<u8>)>,
+    one_sided: bool,
+    max_solutions: Option<usize>,
+    timeout: Option<Duration>,
+    seed: Option<u64>
+}
+
+impl Solver {
+    pub fn new(width: usize, height: usize) -> Solver {
+        Solver {
+            width,
+            height,
+            pieces: ALL_POLYOMINOS.iter().collect(),
+            cell_order: first_open_cell(),
+            constraints: Vec::new(),
+            pins: Vec::new(),
+            allowed_sizes: Vec::new(),
+            one_sided: false,
+            max_solutions: None,
+            timeout: None,
+            seed: None
+        }
+    }
+
+    /// Only these exact pieces are placed, in the orientations given. A
+    /// piece's other rotations and reflections aren't used unless they're
+    /// also listed (see `with_free_pieces`).
+    pub fn with_pieces(mut self, pieces: Vec<&'static Polyomino>) -> Solver {
+        self.pieces = pieces;
+        self
+    }
+
+    /// Lets each of `pieces` be placed in any rotation or reflection.
+    /// Orientations that look the same, like the rotations of the square, are
+    /// only tried once.
+    pub fn with_free_pieces(self, pieces: &[&'static Polyomino]) -> Solver {
+        let mut expanded = Vec::new();
+        for poly in pieces {
+            for orientation in poly.orientations() {
+                if !expanded.contains(&orientation) {
+                    expanded.push(orientation);
+                }
+            }
+        }
+        self.with_pieces(expanded)
+    }
+
+    /// Lets each of `pieces` be placed in any rotation, but never flipped
+    /// over. Since flipping the whole board would flip the pieces too,
+    /// solutions are only treated as copies of each other when one is a
+    /// rotation of the other.
+    pub fn with_one_sided_pieces(mut self, pieces: &[&'static Polyomino]) -> Solver {
+        let mut expanded = Vec::new();
+        for poly in pieces {
+            for turns in 0..4 {
+                let orientation = poly.transform(Symmetry::rotation(turns));
+                if !expanded.contains(&orientation) {
+                    expanded.push(orientation);
+                }
+            }
+        }
+        self.one_sided = true;
+        self.with_pieces(expanded)
+    }
+
+    pub fn with_cell_order(mut self, cell_order: CellOrder) -> Solver {
+        self.cell_order = cell_order;
+        self
+    }
+
+    pub fn with_constraint<C>(mut self, constraint: C) -> Solver
+    where C: Constraint + 'static {
+        self.constraints.push(Box::new(constraint));
+        self
+    }
+
+    /// Places these pieces, with their (0, 0) coord at the given position,
+    /// before the search starts, so every solution contains them. Solutions
+    /// are only treated as symmetric copies under symmetries that leave the
+    /// pins where they are.
+    pub fn with_pins(mut self, pins: Vec<(&'static Polyomino, (i8, i8))>) -> Solver {
+        self.pins = pins;
+        self
+    }
+
+    /// Only lets pieces of these sizes cover `cell` (see
+    /// `Board::allow_only_sizes`). Solutions are only treated as symmetric
+    /// copies under symmetries that keep every cell's rule the same.
+    pub fn with_allowed_sizes(mut self, cell: (i8, i8), sizes: &[u8]) -> Solver {
+        self.allowed_sizes.push((cell, sizes.to_vec()));
+        self
+    }
+
+    // The board the search starts from, before any pins are placed.
+    fn empty_board(&self) -> Option<Board> {
+        // Room for as many pieces as could possibly fit, so the boards the
+        // search clones never have to grow.
+        let smallest = self.pieces.iter().map(|poly| poly.size() as usize).min().unwrap_or(1);
+        let mut board = Board::with_capacity_hint(self.width, self.height, self.width * self.height / smallest);
+        for ((x, y), sizes) in &self.allowed_sizes {
+            board.allow_only_sizes(*x, *y, sizes).ok()?;
+        }
+        Some(board)
+    }
+
+    // The board the search starts from, or None if the pins or the allowed
+    // sizes don't fit.
+    fn start_board(&self) -> Option<Board> {
+        let mut board = self.empty_board()?;
+        for &(poly, base) in &self.pins {
+            if !board.place_at(poly, base) {
+                return None;
+            }
+        }
+        Some(board)
+    }
+
+    /// Stops the search once this many solutions have been found.
+    pub fn with_max_solutions(mut self, max_solutions: usize) -> Solver {
+        self.max_solutions = Some(max_solutions);
+        self
+    }
+
+    /// Stops the search once it has run for this long.
+    pub fn with_timeout(mut self, timeout: Duration) -> Solver {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Tries the pieces in a different order at every step, shuffled by
+    /// `seed`. The same seed always searches in the same order, and every
+    /// solution is still found, but different seeds reach them in different
+    /// orders. Useful with `with_max_solutions` to race searches against
+    /// each other.
+    pub fn with_seed(mut self, seed: u64) -> Solver {
+        self.seed = Some(seed);
+        self
+    }
+
+    // The board symmetries that map the piece set onto itself. Solutions are
+    // only deduplicated under these, so that a canonical form never uses an
+    // orientation that wasn't allowed.
+    fn symmetries(&self) -> Vec<Symmetry> {
+        let board = self.start_board();
+        Symmetry::ALL_SYMMETRIES.iter()
+            .copied()
+            .filter(|&symmetry| self.pieces.iter().all(|poly| self.pieces.contains(&poly.transform(symmetry))))
+            .filter(|&symmetry| board.as_ref().is_none_or(|board| board.allowed_sizes_symmetric(symmetry)))
+            .filter(|&symmetry| board.as_ref().is_none_or(|board| board.pieces_symmetric(symmetry)))
+            .filter(|&symmetry| !self.one_sided || symmetry.is_rotation())
+            .collect()
+    }
+
+    fn allows(&self, board: &Board, poly: &'static Polyomino, base: (i8, i8)) -> bool {
+        self.constraints.iter().all(|c| c.allows_placement(board, poly, base))
+    }
+
+    fn accepts(&self, board: &Board) -> bool {
+        self.constraints.iter().all(|c| c.accepts_solution(board))
+    }
+
+    fn limit_reached(&self, found: usize) -> Option<StopReason> {
+        match self.max_solutions {
+            Some(max_solutions) if found >= max_solutions => Some(StopReason::MaxSolutions),
+            _ => None
+        }
+    }
+
+    /// Calls `on_full` for every full board the search reaches, including
+    /// boards that are symmetric copies of each other. Returns why the search
+    /// stopped early, if it did.
+    pub fn for_each_full_board<F>(&self, on_full: F) -> Option<StopReason>
+    where F: FnMut(&Board) {
+        self.start_board().and_then(|start| self.for_each_completion(start, on_full))
+    }
+
+    /// Like `for_each_full_board`, but starts from a partially-filled board
+    /// and only reports the ways to finish it.
+    pub fn for_each_completion<F>(&self, start: Board, mut on_full: F) -> Option<StopReason>
+    where F: FnMut(&Board) {
+        let mut found = 0;
+        self.search(start, |board| {
+            on_full(board);
+            found += 1;
+            self.limit_reached(found)
+        })
+    }
+
+    // The cloning search. `on_full` returns a reason to stop early, which
+    // can be anything a `StopReason` converts into.
+    fn search<F, R>(&self, start: Board, mut on_full: F) -> Option<R>
+    where F: FnMut(&Board) -> Option<R>, R: From<StopReason> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut rng = self.seed.map(Rng);
+        if start.is_full() {
+            return if self.accepts(&start) { on_full(&start) } else { None };
+        }
+        let mut stack = vec![start];
+
+        while let Some(board) = stack.pop() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Some(StopReason::Timeout.into());
+            }
+            let cell = match (self.cell_order)(&board) {
+                Some(cell) => cell,
+                None => continue
+            };
+            let order = PieceOrder::new(&mut rng, self.pieces.len());
+            for k in 0..self.pieces.len() {
+                let poly = self.pieces[order.index(k, self.pieces.len())];
+                for base in board.placements_covering(poly, cell) {
+                    if !self.allows(&board, poly, base) {
+                        continue;
+                    }
+                    let new_board = board.add_clone_at(poly, base).unwrap();
+                    if new_board.is_full() {
+                        if self.accepts(&new_board) {
+                            if let Some(reason) = on_full(&new_board) {
+                                return Some(reason);
+                            }
+                        }
+                    } else {
+                        stack.push(new_board);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Same as `for_each_full_board`, but descends and backtracks on a single
+    /// board using `place_at` and `remove_last` instead of cloning the board
+    /// for every branch.
+    pub fn for_each_full_board_in_place<F>(&self, mut on_full: F) -> Option<StopReason>
+    where F: FnMut(&Board) {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut rng = self.seed.map(Rng);
+        let mut found = 0;
+        let mut board = self.start_board()?;
+        let mut log = Vec::new();
+        if board.is_full() {
+            if self.accepts(&board) {
+                on_full(&board);
+            }
+            return None;
+        } else if let Some(cell) = (self.cell_order)(&board) {
+            log.push(Move {
+                cell,
+                order: PieceOrder::new(&mut rng, self.pieces.len()),
+                piece: 0,
+                offset: 0,
+                placed: false
+            });
+        }
+
+        while let Some(current) = log.last_mut() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Some(StopReason::Timeout);
+            }
+            if current.placed {
+                board.remove_last();
+                current.placed = false;
+            }
+
+            let mut next = None;
+            while current.piece < self.pieces.len() {
+                let poly = self.pieces[current.order.index(current.piece, self.pieces.len())];
+                if let Some(&(poly_x, poly_y)) = poly.cells().get(current.offset) {
+                    current.offset += 1;
+                    let base = (current.cell.0 - poly_x, current.cell.1 - poly_y);
+                    if board.fits_at(poly, base) && self.allows(&board, poly, base) {
+                        next = Some((poly, base));
+                        break;
+                    }
+                } else {
+                    current.piece += 1;
+                    current.offset = 0;
+                }
+            }
+
+            match next {
+                Some((poly, base)) => {
+                    board.place_at(poly, base);
+                    current.placed = true;
+                    if board.is_full() {
+                        if self.accepts(&board) {
+                            on_full(&board);
+                            found += 1;
+                            if let Some(reason) = self.limit_reached(found) {
+                                return Some(reason);
+                            }
+                        }
+                    } else if let Some(cell) = (self.cell_order)(&board) {
+                        log.push(Move {
+                            cell,
+                            order: PieceOrder::new(&mut rng, self.pieces.len()),
+                            piece: 0,
+                            offset: 0,
+                            placed: false
+                        });
+                    }
+                },
+                None => {
+                    log.pop();
+                }
+            }
+        }
+        None
+    }
+
+    /// Every distinct solution, up to symmetry, sorted. `max_solutions`
+    /// counts distinct solutions here rather than full boards.
+    pub fn solve(&self) -> SearchOutcome {
+        let symmetries = self.symmetries();
+        let mut solutions = BTreeSet::new();
+        let reason = self.start_board().and_then(|start| {
+            self.search(start, |board| {
+                solutions.insert(board.canonical_form_under(&symmetries));
+                self.limit_reached(solutions.len())
+            })
+        });
+        SearchOutcome {
+            solutions: solutions.into_iter().collect(),
+            completed: reason.is_none(),
+            reason
+        }
+    }
+
+    /// Every distinct board, up to symmetry, that leaves at most
+    /// `max_uncovered` cells empty and can't fit any more pieces, including
+    /// full boards. Boards are given in `Board::partial_canonical_form`, sorted by their
+    /// grids. Constraints check these boards with `accepts_solution` as if
+    /// they were full, and `max_solutions` counts distinct boards.
+    pub fn solve_uncovered(&self, max_uncovered: usize) -> UncoveredOutcome {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let symmetries = self.symmetries();
+        let mut found = BTreeMap::new();
+        let mut stack: Vec<(Board, Vec<(i8, i8)>)> = self.start_board().into_iter()
+            .map(|board| (board, Vec::new()))
+            .collect();
+
+        let mut reason = None;
+        while let Some((board, skipped)) = stack.pop() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                reason = Some(StopReason::Timeout);
+                break;
+            }
+            // The cell order only sees the cells that are still undecided.
+            let undecided = board.with_cells_covered(&skipped);
+            let cell = match (self.cell_order)(&undecided) {
+                Some(cell) => cell,
+                None => {
+                    if (board.is_full() || board.is_stuck(&self.pieces)) && self.accepts(&board) {
+                        let canonical = board.partial_canonical_form(&symmetries);
+                        found.insert(canonical.to_grid(), canonical);
+                        reason = self.limit_reached(found.len());
+                        if reason.is_some() {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            if skipped.len() < max_uncovered {
+                let mut skipped = skipped.clone();
+                skipped.push(cell);
+                stack.push((board.clone(), skipped));
+            }
+            for &poly in &self.pieces {
+                // Placements on `undecided` can't cover a skipped cell, which
+                // would find the same board again.
+                for base in undecided.placements_covering(poly, cell) {
+                    if !self.allows(&board, poly, base) {
+                        continue;
+                    }
+                    stack.push((board.add_clone_at(poly, base).unwrap(), skipped.clone()));
+                }
+            }
+        }
+        UncoveredOutcome {
+            boards: found.into_values().collect(),
+            completed: reason.is_none(),
+            reason
+        }
+    }
+
+    /// Writes every distinct solution, up to symmetry, to `writer` as it's
+    /// found, and returns how many were written. Only the canonical forms of
+    /// the solutions are kept in memory, not their boards, grouped by
+    /// `Board::fill_signature` so each new solution is only compared with the
+    /// few that share its signature. `max_solutions`
+    /// counts distinct solutions, like in `solve`. Stops at the first error
+    /// from `writer`.
+    pub fn write_solutions<W>(&self, format: SolutionFormat, writer: &mut W) -> io::Result<usize>
+    where W: Write {
+        // Why the search stopped: a limit, or a failed write.
+        enum Stop {
+            Limit,
+            Failed(io::Error)
+        }
+        impl From<StopReason> for Stop {
+            fn from(_: StopReason) -> Stop {
+                Stop::Limit
+            }
+        }
+
+        let symmetries = self.symmetries();
+        let mut seen: HashMap<u64, Vec<Solution>> = HashMap::new();
+        let mut written = 0;
+        let stop = self.start_board().and_then(|start| self.search(start, |board| {
+            // Symmetric copies always share a signature, so only solutions in
+            // the same bucket can be the same.
+            let bucket = seen.entry(board.fill_signature()).or_default();
+            let solution = board.canonical_form_under(&symmetries);
+            if bucket.contains(&solution) {
+                return None;
+            }
+            let canonical = Board::from_solution(self.width, self.height, &solution).unwrap();
+            let rows = canonical.to_rle();
+            let line = match format {
+                SolutionFormat::Text => writeln!(writer, "{}", rows),
+                SolutionFormat::JsonLines => {
+                    let id = board.canonical_id_under(&symmetries);
+                    let rows: Vec<_> = rows.split('/').map(|row| format!("\"{}\"", row)).collect();
+                    writeln!(writer, "{{\"id\":\"{:016x}\",\"rows\":[{}]}}", id, rows.join(","))
+                }
+            };
+            if let Err(e) = line {
+                return Some(Stop::Failed(e));
+            }
+            bucket.push(solution);
+            written += 1;
+            self.limit_reached(written).map(Stop::from)
+        }));
+        match stop {
+            Some(Stop::Failed(e)) => Err(e),
+            Some(Stop::Limit) | None => Ok(written)
+        }
+    }
+
+    /// Like `solve`, but each canonical solution comes with all of its
+    /// distinct symmetric copies, canonical form first.
+    pub fn solve_orbits(&self) -> Vec<Vec<Solution>> {
+        let symmetries = self.symmetries();
+        self.solve().solutions.iter()
+            .map(|solution| {
+                Board::from_solution(self.width, self.height, solution)
+                    .unwrap()
+                    .orbit_under(&symmetries)
+            })
+            .collect()
+    }
+
+    /// Counts the distinct solutions, up to symmetry, using a bloom filter of
+    /// `bits` bits (rounded up to a multiple of 64) and `hashes` hashes
+    /// instead of storing them, so memory use stays fixed however many
+    /// there are. The count can come up a little short. With n solutions,
+    /// about `bits = 10 * n` and `hashes = 7` gives a 1% false positive rate.
+    pub fn approximate_count(&self, bits: usize, hashes: u32) -> ApproximateCount {
+        let symmetries = self.symmetries();
+        let mut filter = BloomFilter::new(bits, hashes);
+        let mut count = 0;
+        let reason = self.for_each_full_board(|board| {
+            if filter.insert(board.canonical_id_under(&symmetries)) {
+                count += 1;
+            }
+        });
+        ApproximateCount {
+            count,
+            false_positive_rate: filter.false_positive_rate(count),
+            reason
+        }
+    }
+
+    /// The number of full boards, counting symmetric copies separately,
+    /// modulo `modulus`. Nothing is stored, so this works for counts too
+    /// large to hold or to fit in a u64. If the search stopped early, the
+    /// count only covers the boards found before then, and the reason is
+    /// given.
+    pub fn count_modulo(&self, modulus: u64) -> (u64, Option<StopReason>) {
+        assert!(modulus > 0, "modulus must be positive");
+        let mut count = 0;
+        let reason = self.for_each_full_board_in_place(|_| {
+            count = (count + 1) % modulus;
+        });
+        (count, reason)
+    }
+
+    /// The same count as `count_modulo` without the modulus, but found by
+    /// dynamic programming over the frontier instead of a search. Filling
+    /// in reading order, the ways to finish a board only depend on which
+    /// cell is next and which cells past it are already covered, so each of
+    /// those states is only counted once. That's far faster on long, narrow
+    /// boards, where there are few frontiers but many boards.
+    ///
+    /// Returns None if the frontier doesn't fit in 64 cells, which happens
+    /// once the board is wider than about 64 cells divided by the tallest
+    /// piece, or if there are constraints, since they need to see whole
+    /// boards. Also None if the count doesn't fit in a u64. Limits on
+    /// solutions and time are ignored.
+    pub fn count_by_frontier(&self) -> Option<u64> {
+        if !self.constraints.is_empty() {
+            return None;
+        }
+        let board = match self.start_board() {
+            Some(board) => board,
+            None => return Some(0)
+        };
+
+        // Each piece as bit offsets from the cell its (0, 0) coord covers.
+        // (0, 0) is always a piece's first cell in reading order, so the
+        // offsets are never negative.
+        let width = self.width as i64;
+        let mut masks = Vec::with_capacity(self.pieces.len());
+        for &poly in &self.pieces {
+            let mut mask = 0u64;
+            for &(x, y) in poly.coords() {
+                let offset = y as i64 * width + x as i64;
+                if offset >= 64 {
+                    return None;
+                }
+                mask |= 1 << offset;
+            }
+            masks.push((poly, mask));
+        }
+
+        // The ways to finish the board from `cell` on, where bit i of
+        // `frontier` says whether the cell i places later is already covered.
+        // None if the count overflows.
+        fn count(board: &Board, masks: &[(&'static Polyomino, u64)], cell: usize, frontier: u64,
+                 memo: &mut HashMap<(usize, u64), u64>) -> Option<u64> {
+            let width = board.width();
+            if cell == width * board.height() {
+                return Some(1);
+            }
+            let (x, y) = ((cell % width) as i8, (cell / width) as i8);
+            if frontier & 1 != 0 || board.get(x, y) != Some(None) {
+                return count(board, masks, cell + 1, frontier >> 1, memo);
+            }
+            if let Some(&ways) = memo.get(&(cell, frontier)) {
+                return Some(ways);
+            }
+            let ways = masks.iter()
+                .filter(|&&(poly, mask)| frontier & mask == 0 && board.fits_at(poly, (x, y)))
+                .try_fold(0u64, |ways, &(_, mask)| {
+                    ways.checked_add(count(board, masks, cell + 1, (frontier | mask) >> 1, memo)?)
+                })?;
+            memo.insert((cell, frontier), ways);
+            Some(ways)
+        }
+        count(&board, &masks, 0, 0, &mut HashMap::new())
+    }
+
+    /// The `k` distinct solutions with the highest `score`, best first. Only
+    /// `k` solutions are kept in memory at a time. Each solution is scored in
+    /// its canonical orientation, so symmetric copies score the same. If the
+    /// search stopped early, these are only the best of the solutions found
+    /// before then.
+    pub fn top_k<F>(&self, k: usize, score: F) -> SearchOutcome
+    where F: Fn(&Board) -> i64 {
+        let symmetries = self.symmetries();
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        let mut reason = None;
+        if k > 0 {
+            reason = self.for_each_full_board(|board| {
+                let canonical = board.canonical_form_under(&symmetries);
+                if best.iter().any(|Reverse((_, solution))| solution == &canonical) {
+                    return;
+                }
+                let canonical_board = Board::from_solution(self.width, self.height, &canonical)
+                    .expect("canonical forms always fit");
+                best.push(Reverse((score(&canonical_board), canonical)));
+                if best.len() > k {
+                    best.pop();
+                }
+            });
+        }
+        SearchOutcome {
+            solutions: best.into_sorted_vec()
+                .into_iter()
+                .map(|Reverse((_, solution))| solution)
+                .collect(),
+            completed: reason.is_none(),
+            reason
+        }
+    }
+
+    /// Distinct solutions reachable from `solution` by taking two adjacent
+    /// pieces off the board and tiling the hole they leave some other way.
+    /// Pinned pieces are never taken off. Fails if `solution` leaves out a
+    /// pin or breaks the allowed sizes.
+    pub fn neighbors(&self, solution: &Solution) -> Result<Vec<Solution>, Error> {
+        let board = Board::from_solution(self.width, self.height, solution)?;
+        let start = match self.start_board() {
+            Some(start) => start,
+            None => return Ok(Vec::new())
+        };
+        let positions = board.piece_positions();
+        let pinned: Vec<_> = (0..board.piece_count())
+            .filter(|&index| self.pins.contains(&(board.pieces()[index], positions[index])))
+            .collect();
+        // The start board with every piece that's neither pinned nor removed
+        // put back where it was.
+        let rebuild = |removed: &[usize]| {
+            let mut partial = start.clone();
+            for (index, (&poly, &base)) in board.pieces().iter().zip(&positions).enumerate() {
+                if !pinned.contains(&index) && !removed.contains(&index) {
+                    partial.try_place_at(poly, base)?;
+                }
+            }
+            Ok(partial)
+        };
+        rebuild(&[])?;
+
+        let symmetries = self.symmetries();
+        let original = board.canonical_form_under(&symmetries);
+        let mut ret = BTreeSet::new();
+        for (a, b) in board.adjacent_pieces() {
+            if pinned.contains(&a) || pinned.contains(&b) {
+                continue;
+            }
+            self.for_each_completion(rebuild(&[a, b])?, |full| {
+                let canonical = full.canonical_form_under(&symmetries);
+                if canonical != original {
+                    ret.insert(canonical);
+                }
+            });
+        }
+        Ok(ret.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::polyominos::polyominos_where;
+
+    fn find_poly(coords: Vec<(i8, i8)>) -> &'static Polyomino {
+        for poly in ALL_POLYOMINOS.iter() {
+            if poly.cells() == coords.as_slice() {
+                return poly;
+            }
+        }
+        panic!("Can't find poly with coords: {:?}", coords);
+    }
+
+    #[test]
+    fn test_cell_orders_agree() {
+        let pieces: Vec<_> = ALL_POLYOMINOS.iter().collect();
+        let first_open = Solver::new(4, 3).solve().solutions;
+        let min_remaining = Solver::new(4, 3)
+            .with_cell_order(min_remaining_placements(pieces))
+            .solve().solutions;
+        let snake = Solver::new(4, 3)
+            .with_cell_order(snake_order())
+            .solve().solutions;
+
+        assert!(!first_open.is_empty());
+        assert_eq!(first_open, min_remaining);
+        assert_eq!(first_open, snake);
+    }
+
+    #[test]
+    fn test_in_place_search_matches() {
+        let solver = Solver::new(4, 3);
+        let mut cloned = BTreeSet::new();
+        let mut cloned_count = 0;
+        solver.for_each_full_board(|board| {
+            cloned.insert(board.cannonical_form());
+            cloned_count += 1;
+        });
+
+        let mut in_place = BTreeSet::new();
+        let mut in_place_count = 0;
+        solver.for_each_full_board_in_place(|board| {
+            in_place.insert(board.cannonical_form());
+            in_place_count += 1;
+        });
+
+        assert_eq!(cloned, in_place);
+        assert_eq!(cloned_count, in_place_count);
+    }
+
+    #[test]
+    fn test_allowed_sizes() {
+        // The middle of a 3x3 can only be covered by a tetromino.
+        let solver = Solver::new(3, 3).with_allowed_sizes((1, 1), &[4]);
+        let solutions = solver.solve().solutions;
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            let board = Board::from_solution(3, 3, solution).unwrap();
+            assert_eq!(board.polyomino_at(1, 1).unwrap().size(), 4);
+        }
+        let expected = Solver::new(3, 3).solve().solutions.iter()
+            .filter(|solution| {
+                let board = Board::from_solution(3, 3, solution).unwrap();
+                board.polyomino_at(1, 1).unwrap().size() == 4
+            })
+            .count();
+        assert_eq!(solutions.len(), expected);
+
+        // A rule in one corner breaks the board's symmetry.
+        let cornered = Solver::new(3, 3).with_allowed_sizes((0, 0), &[1]);
+        assert_eq!(cornered.symmetries().len(), 2);
+        let mut count = 0;
+        cornered.for_each_full_board(|board| {
+            assert_eq!(board.polyomino_at(0, 0).unwrap().size(), 1);
+            count += 1;
+        });
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_search_outcome() {
+        let tetrominoes = ALL_POLYOMINOS.iter().filter(|p| p.size() == 4).collect();
+        let untileable = Solver::new(3, 3).with_pieces(tetrominoes).solve();
+        assert!(untileable.completed);
+        assert_eq!(untileable.reason, None);
+        assert!(untileable.solutions.is_empty());
+
+        let timed_out = Solver::new(6, 6).with_timeout(Duration::from_secs(0)).solve();
+        assert!(!timed_out.completed);
+        assert_eq!(timed_out.reason, Some(StopReason::Timeout));
+
+        let limited = Solver::new(4, 3).with_max_solutions(3).solve();
+        assert!(!limited.completed);
+        assert_eq!(limited.reason, Some(StopReason::MaxSolutions));
+        assert_eq!(limited.solutions.len(), 3);
+
+        let mut count = 0;
+        let reason = Solver::new(4, 3).with_max_solutions(3).for_each_full_board_in_place(|_| count += 1);
+        assert_eq!(reason, Some(StopReason::MaxSolutions));
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_count_modulo() {
+        let solver = Solver::new(4, 3);
+        let mut exact = 0;
+        solver.for_each_full_board(|_| exact += 1);
+        assert!(exact > 1000);
+
+        for modulus in [1, 2, 97, 1_000_000_007] {
+            assert_eq!(solver.count_modulo(modulus), (exact % modulus, None));
+        }
+
+        // A limit cuts the count short, and says so.
+        let limited = Solver::new(4, 3).with_max_solutions(10);
+        assert_eq!(limited.count_modulo(7), (3, Some(StopReason::MaxSolutions)));
+        let timed_out = Solver::new(4, 3).with_timeout(Duration::from_secs(0));
+        assert_eq!(timed_out.count_modulo(u64::MAX).1, Some(StopReason::Timeout));
+    }
+
+    #[test]
+    fn test_solve_uncovered() {
+        let tetrominos = polyominos_where(|poly| poly.size() == 4);
+        let solver = Solver::new(3, 3).with_pieces(tetrominos.clone());
+        assert!(solver.solve_uncovered(0).boards.is_empty());
+
+        let outcome = solver.solve_uncovered(1);
+        assert!(outcome.completed);
+        assert_eq!(outcome.reason, None);
+        let boards = outcome.boards;
+        assert!(!boards.is_empty());
+        for board in &boards {
+            assert_eq!(board.empty_cells().count(), 1);
+            assert_eq!(board.piece_count(), 2);
+            assert!(board.is_stuck(&tetrominos));
+        }
+        // Each one is only listed once, in its canonical form.
+        for (i, board) in boards.iter().enumerate() {
+            assert_eq!(board.partial_canonical_form(&Symmetry::ALL_SYMMETRIES), *board);
+            assert!(!boards[..i].contains(board));
+        }
+
+        // With more room to spare, boards with a single piece can get stuck.
+        let loose = solver.solve_uncovered(5).boards;
+        assert!(loose.iter().all(|board| board.empty_cells().count() <= 5 && board.is_stuck(&tetrominos)));
+        assert!(loose.iter().any(|board| board.piece_count() == 1));
+        assert!(boards.iter().all(|board| loose.contains(board)));
+
+        // Full boards count too.
+        let monominos = Solver::new(2, 2).with_pieces(polyominos_where(|poly| poly.size() == 1));
+        assert_eq!(monominos.solve_uncovered(2).boards.len(), 1);
+
+        // Constraints, limits and the cell order apply like in `solve`.
+        let balanced = Solver::new(3, 3).with_pieces(tetrominos.clone())
+            .with_constraint(crate::constraints::ChiralBalance)
+            .solve_uncovered(5).boards;
+        assert!(!balanced.is_empty() && balanced.len() < loose.len());
+        assert!(balanced.iter().all(|board| crate::constraints::ChiralBalance.accepts_solution(board)));
+        let limited = Solver::new(3, 3).with_pieces(tetrominos.clone()).with_max_solutions(2);
+        let outcome = limited.solve_uncovered(1);
+        assert_eq!(outcome.boards.len(), 2);
+        assert!(!outcome.completed);
+        assert_eq!(outcome.reason, Some(StopReason::MaxSolutions));
+        let timed_out = Solver::new(3, 3).with_pieces(tetrominos.clone()).with_timeout(Duration::from_secs(0));
+        assert_eq!(timed_out.solve_uncovered(1).reason, Some(StopReason::Timeout));
+        let snake = Solver::new(3, 3).with_pieces(tetrominos.clone()).with_cell_order(snake_order());
+        assert_eq!(snake.solve_uncovered(1).boards, boards);
+    }
+
+    #[test]
+    fn test_solve_orbits() {
+        let solver = Solver::new(3, 3);
+        let orbits = solver.solve_orbits();
+        let solutions = solver.solve().solutions;
+        assert_eq!(orbits.len(), solutions.len());
+
+        for (orbit, canonical) in orbits.iter().zip(&solutions) {
+            assert_eq!(&orbit[0], canonical);
+            let board = Board::from_solution(3, 3, canonical).unwrap();
+            // Symmetries that leave the board alone shrink its orbit.
+            assert_eq!(orbit.len() * board.board_symmetries().len(), 8);
+            for solution in orbit {
+                let copy = Board::from_solution(3, 3, solution).unwrap();
+                assert_eq!(&copy.cannonical_form(), canonical);
+            }
+        }
+        assert!(orbits.iter().any(|orbit| orbit.len() == 8));
+        assert!(orbits.iter().any(|orbit| orbit.len() < 8));
+    }
+
+    #[test]
+    fn test_write_solutions() {
+        let solver = Solver::new(3, 3);
+        let distinct = solver.solve().solutions.len();
+
+        let mut text = Vec::new();
+        assert_eq!(solver.write_solutions(SolutionFormat::Text, &mut text).unwrap(), distinct);
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().count(), distinct);
+        assert!(text.lines().all(|line| line.matches('/').count() == 2));
+
+        let mut json = Vec::new();
+        assert_eq!(solver.write_solutions(SolutionFormat::JsonLines, &mut json).unwrap(), distinct);
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count(), distinct);
+        assert!(json.lines().all(|line| line.starts_with("{\"id\":\"") && line.ends_with("\"]}")));
+
+        let mut limited = Vec::new();
+        let count = solver.with_max_solutions(3)
+            .write_solutions(SolutionFormat::Text, &mut limited).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(String::from_utf8(limited).unwrap().lines().count(), 3);
+
+        // Every solution is written in its canonical form, once.
+        let expected: Vec<_> = Solver::new(3, 3).solve().solutions.iter()
+            .map(|solution| Board::from_solution(3, 3, solution).unwrap().to_rle())
+            .collect();
+        let mut written: Vec<_> = text.lines().map(str::to_string).collect();
+        written.sort_by_key(|rows| expected.iter().position(|e| e == rows).unwrap());
+        assert_eq!(written, expected);
+
+        // Lines still read back once there are more than ten pieces.
+        let dominos = Solver::new(5, 4).with_pieces(polyominos_where(|poly| poly.size() == 2));
+        let mut many = Vec::new();
+        let count = dominos.write_solutions(SolutionFormat::Text, &mut many).unwrap();
+        let many = String::from_utf8(many).unwrap();
+        assert_eq!(count, dominos.solve().solutions.len());
+        for line in many.lines() {
+            let board = Board::from_rle(line, 5, 4).unwrap();
+            assert_eq!(board.piece_count(), 10);
+            assert_eq!(board.to_rle(), line);
+        }
+
+        // The writer's own error comes back.
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let error = Solver::new(3, 3).write_solutions(SolutionFormat::Text, &mut Broken).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_seed() {
+        // Shuffling never loses solutions.
+        let unseeded = Solver::new(4, 3).solve().solutions;
+        assert_eq!(Solver::new(4, 3).with_seed(7).solve().solutions, unseeded);
+        let mut count = 0;
+        Solver::new(4, 3).with_seed(7).for_each_full_board_in_place(|_| count += 1);
+        assert_eq!((count, None), Solver::new(4, 3).count_modulo(u64::MAX));
+
+        let first = |seed| {
+            let mut first = None;
+            Solver::new(6, 6)
+                .with_seed(seed)
+                .with_max_solutions(1)
+                .for_each_full_board(|board| first = Some(board.clone()));
+            first.unwrap()
+        };
+        let (a, b) = (first(1), first(2));
+        assert!(a.is_full() && b.is_full());
+        assert_ne!(a, b);
+        // The same seed always takes the same path.
+        assert_eq!(first(1), a);
+    }
+
+    #[test]
+    fn test_approximate_count() {
+        let solver = Solver::new(4, 3);
+        let exact = solver.solve().solutions.len();
+
+        let roomy = solver.approximate_count(1 << 20, 7);
+        assert!(roomy.false_positive_rate < 1e-6);
+        assert_eq!(roomy.count, exact);
+        assert_eq!(roomy.reason, None);
+
+        // A cramped filter misses some, but about as many as it says.
+        let cramped = solver.approximate_count(4 * exact, 3);
+        assert!(cramped.false_positive_rate > 0.1);
+        assert!(cramped.count < exact);
+        let missed = (exact - cramped.count) as f64;
+        assert!(missed <= cramped.count as f64 * cramped.false_positive_rate);
+
+        let timed_out = Solver::new(6, 6).with_timeout(Duration::from_secs(0)).approximate_count(1 << 20, 7);
+        assert_eq!(timed_out.reason, Some(StopReason::Timeout));
+    }
+
+    #[test]
+    fn test_top_k() {
+        // Favor boards with big pieces in the top row.
+        let score = |board: &Board| {
+            (0..3).map(|x| board.pieces()[board.get(x, 0).unwrap().unwrap()].size() as i64).sum()
+        };
+        let solver = Solver::new(3, 3);
+        let outcome = solver.top_k(5, score);
+        assert!(outcome.completed);
+        let top = outcome.solutions;
+        assert_eq!(top.len(), 5);
+
+        let mut all: Vec<_> = solver.solve().solutions.into_iter()
+            .map(|s| score(&Board::from_solution(3, 3, &s).unwrap()))
+            .collect();
+        all.sort_unstable_by(|a, b| b.cmp(a));
+
+        let top_scores: Vec<_> = top.iter()
+            .map(|s| score(&Board::from_solution(3, 3, s).unwrap()))
+            .collect();
+        assert_eq!(top_scores, all[..5].to_vec());
+
+        let unique: BTreeSet<_> = top.iter().collect();
+        assert_eq!(unique.len(), 5);
+        assert!(solver.top_k(0, score).solutions.is_empty());
+
+        let timed_out = Solver::new(6, 6).with_timeout(Duration::from_secs(0)).top_k(5, score);
+        assert!(!timed_out.completed);
+        assert_eq!(timed_out.reason, Some(StopReason::Timeout));
+    }
+
+    #[test]
+    fn test_exact_cover() {
+        let pieces: Vec<_> = ALL_POLYOMINOS.iter().collect();
+        let board = Board::new(3, 2);
+        let (rows, columns) = to_exact_cover(&board, &pieces);
+        assert_eq!(columns, 6 + pieces.len());
+
+        let placements: usize = pieces.iter()
+            .map(|poly| {
+                (0..2).flat_map(|y| (0..3).map(move |x| (x, y)))
+                    .filter(|&base| board.fits_at(poly, base))
+                    .count()
+            })
+            .sum();
+        assert_eq!(rows.len(), placements);
+
+        for row in &rows {
+            let piece_index = row.last().unwrap() - 6;
+            let poly = pieces[piece_index];
+            let cells = &row[..row.len() - 1];
+            assert_eq!(cells.len(), poly.size() as usize);
+
+            // The row's cell columns are the piece's coords shifted to its base.
+            let base = cells[0] as i8;
+            let (base_x, base_y) = (base % 3, base / 3);
+            let mut expected: Vec<_> = poly.coords()
+                .map(|(x, y)| (base_x + x) as usize + (base_y + y) as usize * 3)
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(cells, &expected[..]);
+        }
+    }
+
+    #[test]
+    fn test_piece_count_bounds() {
+        let tetrominos = polyominos_where(|poly| poly.size() == 4);
+        assert_eq!(piece_count_bounds(&Board::new(6, 6), &tetrominos), (9, 9));
+
+        let all: Vec<_> = ALL_POLYOMINOS.iter().collect();
+        assert_eq!(piece_count_bounds(&Board::new(5, 3), &all), (4, 15));
+
+        // Only the empty cells count. Five of them can't be filled with
+        // tetrominos, so the bounds cross.
+        let square = tetrominos.iter().find(|poly| poly.bounding_box() == (2, 2)).unwrap();
+        let mut board = Board::new(3, 3);
+        assert!(board.place_at(square, (0, 0)));
+        assert_eq!(piece_count_bounds(&board, &tetrominos), (2, 1));
+    }
+
+    #[test]
+    fn test_tile_with_single() {
+        let domino = ALL_POLYOMINOS.iter().find(|p| p.size() == 2).unwrap();
+        // Tilings of a 2xN strip by dominoes follow the Fibonacci numbers.
+        assert_eq!(tile_with_single(1, 2, domino).len(), 1);
+        assert_eq!(tile_with_single(2, 2, domino).len(), 2);
+        assert_eq!(tile_with_single(3, 2, domino).len(), 3);
+        assert_eq!(tile_with_single(4, 2, domino).len(), 5);
+        assert_eq!(tile_with_single(2, 4, domino).len(), 5);
+
+        assert!(tile_with_single(3, 3, domino).is_empty());
+    }
+
+    #[test]
+    fn test_free_pieces() {
+        let square = find_poly(vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        let line = find_poly(vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let leg = find_poly(vec![(0, 0), (0, 1), (0, 2), (1, 2)]);
+        assert_eq!(square.orientations().len(), 1);
+        assert_eq!(line.orientations().len(), 2);
+        assert_eq!(leg.orientations().len(), 8);
+
+        // One placement of the square per position, so one way to fill a 2x2.
+        let mut count = 0;
+        Solver::new(2, 2).with_free_pieces(&[square]).for_each_full_board(|_| count += 1);
+        assert_eq!(count, 1);
+
+        // Listing the same piece twice doesn't double the placements either.
+        let mut count = 0;
+        Solver::new(4, 4).with_free_pieces(&[square, square]).for_each_full_board(|_| count += 1);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_locked_orientations() {
+        let line = find_poly(vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let tall_line = find_poly(vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+
+        let solutions = Solver::new(4, 4).with_pieces(vec![line]).solve().solutions;
+        assert_eq!(solutions.len(), 1);
+        for solution in &solutions {
+            assert!(solution.iter().all(|&poly| poly == line));
+        }
+
+        // With both orientations allowed, rotating the board is fair game.
+        let free = Solver::new(4, 4).with_free_pieces(&[line]).solve().solutions;
+        assert!(free.iter().any(|solution| solution.contains(&tall_line)));
+    }
+
+    #[test]
+    fn test_one_sided_pieces() {
+        // XXX
+        // X
+        let l_piece = find_poly(vec![(0, 0), (0, 1), (1, 0), (2, 0)]);
+        // XXX
+        //  X
+        let t_piece = find_poly(vec![(0, 0), (1, 0), (1, 1), (2, 0)]);
+        let l_form = l_piece.one_sided_form();
+        assert!(l_piece.is_chiral());
+
+        let solver = Solver::new(4, 4).with_one_sided_pieces(&[l_piece, t_piece]);
+        assert!(solver.symmetries().iter().all(|symmetry| symmetry.is_rotation()));
+        let solutions = solver.solve().solutions;
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            for &poly in solution {
+                assert!(poly.one_sided_form() == l_form || poly.one_sided_form() == t_piece.one_sided_form());
+            }
+        }
+
+        // Flipping is what lets the free pieces find the rest.
+        let free = Solver::new(4, 4).with_free_pieces(&[l_piece, t_piece]).solve().solutions;
+        let flipped = |solution: &Solution| solution.iter().any(|&poly| poly.one_sided_form() == l_piece.mirror_form());
+        assert!(free.iter().any(flipped));
+        assert!(!solutions.iter().any(flipped));
+    }
+
+    #[test]
+    fn test_count_by_frontier() {
+        // X
+        // X
+        let domino = find_poly(vec![(0, 0), (0, 1)]);
+        // Domino tilings of 4xN boards.
+        let expected = [1, 5, 11, 36, 95, 281, 781, 2245];
+        for (height, &tilings) in (1..).zip(&expected) {
+            let solver = Solver::new(4, height).with_free_pieces(&[domino]);
+            assert_eq!(solver.count_by_frontier(), Some(tilings));
+            assert_eq!(solver.count_modulo(u64::MAX), (tilings, None));
+        }
+        // Far too many for the search to get through. From the recurrence
+        // a(n) = a(n-1) + 5a(n-2) + a(n-3) - a(n-4).
+        let long = Solver::new(4, 40).with_free_pieces(&[domino]);
+        assert_eq!(long.count_by_frontier(), Some(721_463_601_671_126_161));
+        // 4x43 is the longest whose count fits in a u64.
+        let longest = Solver::new(4, 43).with_free_pieces(&[domino]);
+        assert_eq!(longest.count_by_frontier(), Some(16_535_426_751_448_337_051));
+        let too_long = Solver::new(4, 44).with_free_pieces(&[domino]);
+        assert_eq!(too_long.count_by_frontier(), None);
+
+        let solver = Solver::new(4, 3).with_pins(vec![(domino, (1, 1))]);
+        assert_eq!(solver.count_by_frontier(), Some(solver.count_modulo(u64::MAX).0));
+        let solver = Solver::new(3, 3).with_allowed_sizes((1, 1), &[4]);
+        assert_eq!(solver.count_by_frontier(), Some(solver.count_modulo(u64::MAX).0));
+
+        assert_eq!(Solver::new(4, 2).with_constraint(crate::constraints::ChiralBalance).count_by_frontier(), None);
+        assert_eq!(Solver::new(40, 2).count_by_frontier(), None);
+    }
+
+    #[test]
+    fn test_pins() {
+        // XXXX
+        let line = find_poly(vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let solver = Solver::new(4, 3).with_pins(vec![(line, (0, 0))]);
+
+        let check = |board: &Board| {
+            let index = board.get(0, 0).unwrap().unwrap();
+            assert_eq!(board.pieces()[index], line);
+            for x in 0..4 {
+                assert_eq!(board.get(x, 0), Some(Some(index)));
+            }
+        };
+        let mut count = 0;
+        solver.for_each_full_board(|board| {
+            check(board);
+            count += 1;
+        });
+        assert!(count > 0);
+
+        let mut in_place_count = 0;
+        solver.for_each_full_board_in_place(|board| {
+            check(board);
+            in_place_count += 1;
+        });
+        assert_eq!(count, in_place_count);
+
+        // A pin that hangs off the board leaves nothing to find.
+        let mut found = false;
+        Solver::new(4, 3).with_pins(vec![(line, (1, 0))]).for_each_full_board(|_| found = true);
+        assert!(!found);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let solver = Solver::new(2, 2);
+        // Two dominoes side by side.
+        let dominoes = solver.solve().solutions.into_iter()
+            .find(|s| s.iter().map(|p| p.size()).collect::<Vec<_>>() == vec![2, 2])
+            .unwrap();
+
+        let neighbors = solver.neighbors(&dominoes).unwrap();
+        assert!(!neighbors.is_empty());
+        assert!(!neighbors.contains(&dominoes));
+
+        // Swapping the dominoes for the square is one of the moves.
+        let square = solver.solve().solutions.into_iter()
+            .find(|s| s.len() == 1)
+            .unwrap();
+        assert!(neighbors.contains(&square));
+    }
+
+    #[test]
+    fn test_neighbors_keep_pins() {
+        // XX
+        // XX
+        let square = find_poly(vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        let solver = Solver::new(4, 3).with_pins(vec![(square, (1, 1))]).with_allowed_sizes((0, 0), &[1, 2]);
+        let solutions = solver.solve().solutions;
+        let solution = solutions.iter()
+            .find(|s| !solver.neighbors(s).unwrap().is_empty())
+            .unwrap();
+
+        for neighbor in solver.neighbors(solution).unwrap() {
+            assert!(solutions.contains(&neighbor), "{:?}", neighbor);
+            let board = Board::from_solution(4, 3, &neighbor).unwrap();
+            let index = board.get(1, 1).unwrap().unwrap();
+            assert_eq!(board.pieces()[index], square);
+            assert_eq!(board.piece_positions()[index], (1, 1));
+            assert!(board.polyomino_at(0, 0).unwrap().size() <= 2);
+        }
+
+        // Without the pin, there's nothing to keep in place.
+        let unpinned = Solver::new(4, 3).solve().solutions;
+        let moved = unpinned.iter()
+            .find(|s| Board::from_solution(4, 3, s).unwrap().polyomino_at(1, 1) != Some(square))
+            .unwrap();
+        assert!(solver.neighbors(moved).is_err());
+    }
+}
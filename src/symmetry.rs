@@ -1,4 +1,17 @@
 
+/// A line to reflect across, as drawn on the board with y going down.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// An up-down line, so left and right swap.
+    Vertical,
+    /// A left-right line, so top and bottom swap.
+    Horizontal,
+    /// The line from the top left to the bottom right, so x and y swap.
+    Diagonal,
+    /// The line from the top right to the bottom left.
+    AntiDiagonal
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub struct Symmetry {
     pub horizontal: bool,
@@ -26,6 +39,53 @@ impl Symmetry {
         Symmetry { horizontal, vertical, diagonal }
     }
 
+    /// Turning clockwise by this many quarter turns. Negative turns go
+    /// counterclockwise.
+    pub fn rotation(quarter_turns: i8) -> Symmetry {
+        Symmetry::default().rotate(quarter_turns)
+    }
+
+    /// Whether this is one of the four rotations, rather than a reflection.
+    pub fn is_rotation(self) -> bool {
+        // Each rotation flips an even number of ways.
+        !(self.horizontal ^ self.vertical ^ self.diagonal)
+    }
+
+    /// Mirroring across `axis`.
+    pub fn reflection(axis: Axis) -> Symmetry {
+        match axis {
+            Axis::Vertical => Symmetry::from_flips(true, false, false),
+            Axis::Horizontal => Symmetry::from_flips(false, true, false),
+            Axis::Diagonal => Symmetry::from_flips(false, false, true),
+            Axis::AntiDiagonal => Symmetry::from_flips(true, true, true)
+        }
+    }
+
+    /// Where this symmetry moves a coordinate, turning and flipping about (0, 0).
+    /// Flips happen before the diagonal swap, like everywhere else.
+    pub fn apply(self, (mut x, mut y): (i8, i8)) -> (i8, i8) {
+        if self.horizontal {
+            x = -x;
+        }
+        if self.vertical {
+            y = -y;
+        }
+        if self.diagonal {
+            std::mem::swap(&mut x, &mut y);
+        }
+        (x, y)
+    }
+
+    /// This symmetry followed by `next`.
+    pub fn then(self, next: Symmetry) -> Symmetry {
+        // (1, 2) ends up somewhere different under each of the symmetries.
+        let probe = next.apply(self.apply((1, 2)));
+        Symmetry::ALL_SYMMETRIES.iter()
+            .copied()
+            .find(|symmetry| symmetry.apply((1, 2)) == probe)
+            .unwrap()
+    }
+
     const fn from_index_unchecked(index: usize) -> Symmetry {
         Symmetry {
             horizontal: index & Self::HORIZONTAL_MASK != 0,
@@ -100,4 +160,41 @@ impl Symmetry {
         }
         self
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_named_symmetries() {
+        let quarter = Symmetry::rotation(1);
+        let identity = Symmetry::default();
+        assert_eq!(quarter.then(quarter).then(quarter).then(quarter), identity);
+        assert_eq!(quarter.apply((1, 0)), (0, 1));
+
+        for turns in -4..8 {
+            assert_eq!(Symmetry::rotation(turns), identity.rotate(turns));
+            let composed = (0..turns.rem_euclid(4)).fold(identity, |s, _| s.then(quarter));
+            assert_eq!(Symmetry::rotation(turns), composed);
+        }
+        assert_eq!(Symmetry::rotation(-1), Symmetry::rotation(3));
+        assert!((0..4).all(|turns| Symmetry::rotation(turns).is_rotation()));
+
+        let axes = [Axis::Vertical, Axis::Horizontal, Axis::Diagonal, Axis::AntiDiagonal];
+        let mut all: Vec<_> = axes.iter().map(|&axis| Symmetry::reflection(axis)).collect();
+        for &reflection in &all {
+            assert_ne!(reflection, identity);
+            assert_eq!(reflection.then(reflection), identity);
+            assert!(!reflection.is_rotation());
+        }
+        assert_eq!(Symmetry::reflection(Axis::Vertical), identity.mirror_horizontal());
+        assert_eq!(Symmetry::reflection(Axis::Horizontal), identity.mirror_vertical());
+        assert_eq!(Symmetry::reflection(Axis::AntiDiagonal).apply((1, 0)), (0, -1));
+
+        // Four rotations and four reflections make up the whole group.
+        all.extend((0..4).map(Symmetry::rotation));
+        all.sort_by_key(|s| s.into_index());
+        assert_eq!(all, Symmetry::ALL_SYMMETRIES);
+    }
+}